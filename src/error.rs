@@ -19,7 +19,8 @@ use crate::proto::ProtocolError;
 pub enum Error {
     /// Attempted to read from or write to a closed stream.
     AlreadyClosed,
-    /// DNS lookup failed.
+    /// DNS lookup did not yield any address, or the URI has no host. Lookups
+    /// failing with an I/O error are reported as [`Error::Io`] instead.
     CannotResolveHost,
     /// Attempted to connect a client to a remote without configured URI.
     #[cfg(feature = "client")]
@@ -29,8 +30,21 @@ pub enum Error {
     DisallowedHeader,
     /// WebSocket protocol violation.
     Protocol(ProtocolError),
+    /// The remote did not respond to a keepalive ping in time.
+    #[cfg(any(feature = "client", feature = "server"))]
+    KeepaliveTimeout,
+    /// Attempted to send a data message while a message written by a
+    /// [`MessageWriter`] or via [`WebSocketStream::send_frame`] has not been
+    /// finished.
+    ///
+    /// [`MessageWriter`]: crate::proto::MessageWriter
+    /// [`WebSocketStream::send_frame`]: crate::WebSocketStream::send_frame
+    #[cfg(any(feature = "client", feature = "server"))]
+    FragmentationInProgress,
     /// Payload length limit was exceeded.
     PayloadTooLong { len: usize, max_len: usize },
+    /// Frame payload length limit was exceeded.
+    FrameTooLong { len: usize, max_len: usize },
     /// I/O error.
     Io(io::Error),
     /// TLS error originating in [`native_tls`].
@@ -52,9 +66,48 @@ pub enum Error {
         feature = "rustls-bring-your-own-connector"
     ))]
     Rustls(tokio_rustls::rustls::Error),
-    /// An unsupported, i.e. not `ws` or `wss`, or no URI scheme was specified.
+    /// Attempted to configure rustls-specific settings on a connector that
+    /// does not use rustls.
+    #[cfg(any(
+        feature = "rustls-webpki-roots",
+        feature = "rustls-native-roots",
+        feature = "rustls-platform-verifier",
+        feature = "rustls-bring-your-own-connector"
+    ))]
+    NotRustlsConnector,
+    /// An unsupported, i.e. not `ws`, `wss`, `http` or `https`, or no URI scheme
+    /// was specified.
     #[cfg(feature = "client")]
     UnsupportedScheme,
+    /// None of the addresses the hostname resolved to are of the same family as
+    /// the configured local address.
+    #[cfg(feature = "client")]
+    AddressFamilyMismatch,
+    /// Connecting to every address the hostname resolved to failed. Contains
+    /// the error encountered for each address.
+    #[cfg(feature = "client")]
+    AllAddrsFailed(Vec<io::Error>),
+    /// The client did not establish a connection within the configured
+    /// timeout, or no message was received within the timeout passed to
+    /// [`WebSocketStream::recv_timeout`].
+    ///
+    /// [`WebSocketStream::recv_timeout`]: crate::WebSocketStream::recv_timeout
+    #[cfg(any(feature = "client", feature = "server"))]
+    Timeout,
+    /// The server did not complete the HTTP upgrade handshake within the
+    /// configured handshake timeout.
+    #[cfg(feature = "client")]
+    HandshakeTimeout,
+    /// The server redirected the client more often than the configured maximum
+    /// number of redirects.
+    #[cfg(feature = "client")]
+    TooManyRedirects,
+    /// The server redirected a TLS connection to a URI without TLS.
+    #[cfg(feature = "client")]
+    InsecureRedirect,
+    /// The TLS server name is neither a valid DNS name nor an IP address.
+    #[cfg(feature = "client")]
+    InvalidServerName,
     /// The HTTP/1.1 Upgrade failed.
     #[cfg(any(feature = "client", feature = "server"))]
     Upgrade(crate::upgrade::Error),
@@ -118,6 +171,86 @@ impl From<crate::upgrade::Error> for Error {
     }
 }
 
+impl Error {
+    /// Returns whether the error is caused by a timeout, i.e. it is
+    /// `Error::Timeout`, `Error::HandshakeTimeout`,
+    /// `Error::KeepaliveTimeout` or an [`Error::Io`] error of kind
+    /// [`io::ErrorKind::TimedOut`].
+    #[must_use]
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            #[cfg(any(feature = "client", feature = "server"))]
+            Error::Timeout | Error::KeepaliveTimeout => true,
+            #[cfg(feature = "client")]
+            Error::HandshakeTimeout => true,
+            Error::Io(e) => e.kind() == io::ErrorKind::TimedOut,
+            _ => false,
+        }
+    }
+
+    /// Returns whether the error originates in the underlying I/O, i.e. it is
+    /// [`Error::Io`] or `Error::AllAddrsFailed`.
+    #[must_use]
+    pub fn is_io(&self) -> bool {
+        match self {
+            Error::Io(_) => true,
+            #[cfg(feature = "client")]
+            Error::AllAddrsFailed(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns whether the remote violated the WebSocket protocol, i.e. the
+    /// error is [`Error::Protocol`].
+    #[must_use]
+    pub fn is_protocol(&self) -> bool {
+        matches!(self, Error::Protocol(_))
+    }
+
+    /// Returns whether the error originates in TLS, i.e. it is
+    /// `Error::NativeTls`, `Error::Rustls`, `Error::InvalidDNSName`,
+    /// `Error::NotRustlsConnector`, `Error::NoNativeRootCertificatesFound` or
+    /// `Error::InvalidServerName`,
+    /// depending on the enabled features.
+    #[must_use]
+    pub fn is_tls(&self) -> bool {
+        match self {
+            #[cfg(feature = "native-tls")]
+            Error::NativeTls(_) => true,
+            #[cfg(any(
+                feature = "rustls-webpki-roots",
+                feature = "rustls-native-roots",
+                feature = "rustls-platform-verifier",
+                feature = "rustls-bring-your-own-connector"
+            ))]
+            Error::InvalidDNSName(_) | Error::Rustls(_) | Error::NotRustlsConnector => true,
+            #[cfg(all(
+                not(feature = "rustls-webpki-roots"),
+                feature = "rustls-native-roots",
+                not(feature = "rustls-platform-verifier")
+            ))]
+            Error::NoNativeRootCertificatesFound(_) => true,
+            #[cfg(feature = "client")]
+            Error::InvalidServerName => true,
+            _ => false,
+        }
+    }
+
+    /// Returns whether the HTTP upgrade handshake failed, i.e. the error is
+    /// `Error::Upgrade`, `Error::HandshakeTimeout`, `Error::TooManyRedirects`
+    /// or `Error::InsecureRedirect`.
+    #[must_use]
+    pub fn is_handshake(&self) -> bool {
+        match self {
+            #[cfg(any(feature = "client", feature = "server"))]
+            Error::Upgrade(_) => true,
+            #[cfg(feature = "client")]
+            Error::HandshakeTimeout | Error::TooManyRedirects | Error::InsecureRedirect => true,
+            _ => false,
+        }
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -130,12 +263,24 @@ impl fmt::Display for Error {
             #[cfg(any(feature = "client", feature = "server"))]
             Error::DisallowedHeader => f.write_str("attempted to add disallowed header"),
             Error::Protocol(e) => e.fmt(f),
+            #[cfg(any(feature = "client", feature = "server"))]
+            Error::KeepaliveTimeout => f.write_str("keepalive ping timed out"),
+            #[cfg(any(feature = "client", feature = "server"))]
+            Error::FragmentationInProgress => {
+                f.write_str("attempted to send message while another message is being written")
+            }
             Error::PayloadTooLong { len, max_len } => {
                 f.write_str("payload length of ")?;
                 len.fmt(f)?;
                 f.write_str(" exceeds the limit of ")?;
                 max_len.fmt(f)
             }
+            Error::FrameTooLong { len, max_len } => {
+                f.write_str("frame payload length of ")?;
+                len.fmt(f)?;
+                f.write_str(" exceeds the limit of ")?;
+                max_len.fmt(f)
+            }
             Error::Io(e) => e.fmt(f),
             #[cfg(feature = "native-tls")]
             Error::NativeTls(e) => e.fmt(f),
@@ -153,8 +298,36 @@ impl fmt::Display for Error {
                 feature = "rustls-bring-your-own-connector"
             ))]
             Error::Rustls(e) => e.fmt(f),
+            #[cfg(any(
+                feature = "rustls-webpki-roots",
+                feature = "rustls-native-roots",
+                feature = "rustls-platform-verifier",
+                feature = "rustls-bring-your-own-connector"
+            ))]
+            Error::NotRustlsConnector => f.write_str("connector does not use rustls"),
             #[cfg(feature = "client")]
             Error::UnsupportedScheme => f.write_str("unsupported or no URI scheme used"),
+            #[cfg(feature = "client")]
+            Error::AddressFamilyMismatch => {
+                f.write_str("no resolved address matches the family of the local address")
+            }
+            #[cfg(feature = "client")]
+            Error::AllAddrsFailed(e) => {
+                f.write_str("failed to connect to all resolved addresses, errors encountered: ")?;
+                fmt::Debug::fmt(e, f)
+            }
+            #[cfg(any(feature = "client", feature = "server"))]
+            Error::Timeout => f.write_str("operation timed out"),
+            #[cfg(feature = "client")]
+            Error::HandshakeTimeout => f.write_str("upgrade handshake timed out"),
+            #[cfg(feature = "client")]
+            Error::TooManyRedirects => f.write_str("exceeded the maximum number of redirects"),
+            #[cfg(feature = "client")]
+            Error::InsecureRedirect => {
+                f.write_str("refused to follow a redirect from TLS to plain text")
+            }
+            #[cfg(feature = "client")]
+            Error::InvalidServerName => f.write_str("invalid TLS server name"),
             #[cfg(any(feature = "client", feature = "server"))]
             Error::Upgrade(e) => e.fmt(f),
             #[cfg(all(
@@ -173,11 +346,17 @@ impl fmt::Display for Error {
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Error::AlreadyClosed | Error::CannotResolveHost | Error::PayloadTooLong { .. } => None,
+            Error::AlreadyClosed
+            | Error::CannotResolveHost
+            | Error::PayloadTooLong { .. }
+            | Error::FrameTooLong { .. } => None,
             #[cfg(feature = "client")]
             Error::NoUriConfigured => None,
             #[cfg(any(feature = "client", feature = "server"))]
-            Error::DisallowedHeader => None,
+            Error::DisallowedHeader
+            | Error::KeepaliveTimeout
+            | Error::FragmentationInProgress
+            | Error::Timeout => None,
             #[cfg(all(
                 not(feature = "rustls-webpki-roots"),
                 feature = "rustls-native-roots",
@@ -185,7 +364,14 @@ impl std::error::Error for Error {
             ))]
             Error::NoNativeRootCertificatesFound(e) => Some(e.first()?),
             #[cfg(feature = "client")]
-            Error::UnsupportedScheme => None,
+            Error::UnsupportedScheme
+            | Error::AddressFamilyMismatch
+            | Error::HandshakeTimeout
+            | Error::TooManyRedirects
+            | Error::InsecureRedirect
+            | Error::InvalidServerName => None,
+            #[cfg(feature = "client")]
+            Error::AllAddrsFailed(e) => Some(e.first()?),
             Error::Protocol(e) => Some(e),
             Error::Io(e) => Some(e),
             #[cfg(feature = "native-tls")]
@@ -204,6 +390,13 @@ impl std::error::Error for Error {
                 feature = "rustls-bring-your-own-connector"
             ))]
             Error::Rustls(e) => Some(e),
+            #[cfg(any(
+                feature = "rustls-webpki-roots",
+                feature = "rustls-native-roots",
+                feature = "rustls-platform-verifier",
+                feature = "rustls-bring-your-own-connector"
+            ))]
+            Error::NotRustlsConnector => None,
             #[cfg(any(feature = "client", feature = "server"))]
             Error::Upgrade(e) => Some(e),
         }
@@ -0,0 +1,215 @@
+//! Conversion of internationalized hostnames in URIs to their ASCII form
+//! using Punycode, see [RFC 3492](https://datatracker.ietf.org/doc/html/rfc3492).
+//!
+//! Only the Punycode encoding of labels is implemented, preceded by
+//! lowercasing them. The full UTS #46 mapping, which e.g. normalizes the
+//! labels, is not applied, so hostnames are expected to be in Unicode
+//! Normalization Form C, which is what virtually all input methods produce.
+use std::borrow::Cow;
+
+/// Number of digits of the Punycode base.
+const BASE: u32 = 36;
+/// Minimum threshold of a digit.
+const T_MIN: u32 = 1;
+/// Maximum threshold of a digit.
+const T_MAX: u32 = 26;
+/// Skew of the bias adaption.
+const SKEW: u32 = 38;
+/// Damping of the first bias adaption.
+const DAMP: u32 = 700;
+/// Initial bias.
+const INITIAL_BIAS: u32 = 72;
+/// Initial code point, the first one that is not ASCII.
+const INITIAL_N: u32 = 0x80;
+
+/// Characters separating the labels of a hostname, including the full stops
+/// that UTS #46 maps to `.`.
+const SEPARATORS: [char; 4] = ['.', '\u{3002}', '\u{ff0e}', '\u{ff61}'];
+
+/// Adapts the bias after encoding a delta, see RFC 3492, section 6.1.
+fn adapt(mut delta: u32, num_points: u32, first_time: bool) -> u32 {
+    delta /= if first_time { DAMP } else { 2 };
+    delta += delta / num_points;
+
+    let mut offset = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        offset += BASE;
+    }
+
+    offset + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+}
+
+/// Returns the character of a Punycode digit.
+fn digit(value: u32) -> char {
+    // Digits are a-z followed by 0-9
+    let value = u8::try_from(value).expect("digits are less than the base");
+    char::from(if value < 26 {
+        b'a' + value
+    } else {
+        b'0' + value - 26
+    })
+}
+
+/// Encodes a label with Punycode, see RFC 3492, section 6.3. Returns [`None`]
+/// if the encoding overflows.
+fn encode(input: &str) -> Option<String> {
+    let mut output: String = input.chars().filter(char::is_ascii).collect();
+    let basic_len = u32::try_from(output.len()).ok()?;
+    if basic_len > 0 {
+        output.push('-');
+    }
+
+    let mut code_point = INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut handled = basic_len;
+    let total = u32::try_from(input.chars().count()).ok()?;
+
+    while handled < total {
+        // The smallest code point that was not handled yet
+        let next = input
+            .chars()
+            .map(u32::from)
+            .filter(|&c| c >= code_point)
+            .min()?;
+        delta = delta.checked_add((next - code_point).checked_mul(handled + 1)?)?;
+        code_point = next;
+
+        for c in input.chars().map(u32::from) {
+            if c < code_point {
+                delta = delta.checked_add(1)?;
+            }
+
+            if c == code_point {
+                let mut remaining = delta;
+                let mut offset = BASE;
+
+                loop {
+                    let threshold = if offset <= bias {
+                        T_MIN
+                    } else if offset >= bias + T_MAX {
+                        T_MAX
+                    } else {
+                        offset - bias
+                    };
+
+                    if remaining < threshold {
+                        break;
+                    }
+
+                    output.push(digit(
+                        threshold + (remaining - threshold) % (BASE - threshold),
+                    ));
+                    remaining = (remaining - threshold) / (BASE - threshold);
+                    offset += BASE;
+                }
+
+                output.push(digit(remaining));
+                bias = adapt(delta, handled + 1, handled == basic_len);
+                delta = 0;
+                handled += 1;
+            }
+        }
+
+        delta += 1;
+        code_point += 1;
+    }
+
+    Some(output)
+}
+
+/// Converts a hostname to its ASCII form, encoding labels that contain
+/// non-ASCII characters with Punycode. Returns [`None`] if encoding a label
+/// fails.
+fn host_to_ascii(host: &str) -> Option<String> {
+    let mut ascii = String::with_capacity(host.len());
+
+    for (i, label) in host.split(SEPARATORS).enumerate() {
+        if i > 0 {
+            ascii.push('.');
+        }
+
+        if label.is_ascii() {
+            ascii.push_str(label);
+        } else {
+            ascii.push_str("xn--");
+            ascii.push_str(&encode(&label.to_lowercase())?);
+        }
+    }
+
+    Some(ascii)
+}
+
+/// Converts the host of a URI to its ASCII form if it contains non-ASCII
+/// characters, leaving the rest of the URI untouched.
+///
+/// The URI is returned unchanged if it has no such host or the conversion
+/// fails, in which case parsing it fails as before.
+pub fn uri_to_ascii(uri: &str) -> Cow<'_, str> {
+    let Some(authority_start) = uri.find("://").map(|i| i + 3) else {
+        return Cow::Borrowed(uri);
+    };
+
+    let authority_len = uri[authority_start..]
+        .find(['/', '?', '#'])
+        .unwrap_or(uri.len() - authority_start);
+    let authority = &uri[authority_start..authority_start + authority_len];
+
+    let host_start = authority.rfind('@').map_or(0, |i| i + 1);
+    let host_len = authority[host_start..]
+        .rfind(':')
+        .unwrap_or(authority.len() - host_start);
+    let host = &authority[host_start..host_start + host_len];
+
+    if host.is_ascii() {
+        return Cow::Borrowed(uri);
+    }
+
+    let Some(ascii) = host_to_ascii(host) else {
+        return Cow::Borrowed(uri);
+    };
+
+    let start = authority_start + host_start;
+    let end = start + host.len();
+
+    Cow::Owned([&uri[..start], &ascii, &uri[end..]].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode, uri_to_ascii};
+
+    #[test]
+    fn rfc_samples() {
+        // Samples from RFC 3492, section 7.1
+        assert_eq!(
+            encode("\u{4ed6}\u{4eec}\u{4e3a}\u{4ec0}\u{4e48}\u{4e0d}\u{8bf4}\u{4e2d}\u{6587}")
+                .unwrap(),
+            "ihqwcrb4cv8a8dqg056pqjye"
+        );
+        assert_eq!(
+            encode("3\u{5e74}B\u{7d44}\u{91d1}\u{516b}\u{5148}\u{751f}").unwrap(),
+            "3B-ww4c5e180e575a65lsy2b"
+        );
+        assert_eq!(encode("bücher").unwrap(), "bcher-kva");
+    }
+
+    #[test]
+    fn uris() {
+        assert_eq!(
+            uri_to_ascii("wss://bücher.example/path?query"),
+            "wss://xn--bcher-kva.example/path?query"
+        );
+        assert_eq!(
+            uri_to_ascii("ws://user:pass@MÜNCHEN.example:8080/ü"),
+            "ws://user:pass@xn--mnchen-3ya.example:8080/ü"
+        );
+        assert_eq!(
+            uri_to_ascii("wss://例え。テスト"),
+            "wss://xn--r8jz45g.xn--zckzah"
+        );
+        assert_eq!(uri_to_ascii("wss://example.com/ü"), "wss://example.com/ü");
+        assert_eq!(uri_to_ascii("/relative"), "/relative");
+    }
+}
@@ -56,11 +56,15 @@ compile_error!("client and server implementation require at least one SHA1 backe
 #[cfg(feature = "client")]
 pub mod client;
 pub mod error;
+#[cfg(feature = "client")]
+mod idna;
 mod mask;
 pub mod proto;
 #[cfg(feature = "client")]
 mod rand;
 #[cfg(feature = "client")]
+pub mod reconnect;
+#[cfg(feature = "client")]
 pub mod resolver;
 #[cfg(feature = "server")]
 pub mod server;
@@ -74,7 +78,10 @@ mod utf8;
 #[cfg(feature = "client")]
 pub use client::Builder as ClientBuilder;
 pub use error::Error;
-pub use proto::{CloseCode, Config, Limits, Message, Payload, WebSocketStream};
+pub use proto::{
+    CloseCode, Config, Frame, Limits, Message, Payload, SplitSink, SplitStream, WebSocketStream,
+};
 #[cfg(feature = "server")]
 pub use server::Builder as ServerBuilder;
 pub use tls::{Connector, MaybeTlsStream};
+pub use utf8::Validator as Utf8Validator;
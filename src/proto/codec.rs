@@ -144,6 +144,13 @@ impl Decoder for WebSocketProtocol {
             }
         }
 
+        if payload_length > self.limits.max_frame_len {
+            return Err(Error::FrameTooLong {
+                len: payload_length,
+                max_len: self.limits.max_frame_len,
+            });
+        }
+
         if payload_length > self.limits.max_payload_len {
             return Err(Error::PayloadTooLong {
                 len: payload_length,
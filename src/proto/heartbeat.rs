@@ -0,0 +1,131 @@
+//! [`WebSocketStream`] wrapper that sends application-level heartbeat
+//! messages on a fixed interval.
+use std::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    time::{Instant, Interval, MissedTickBehavior, interval_at},
+};
+
+use super::{Message, WebSocketStream, types::StreamState};
+use crate::Error;
+
+/// A [`WebSocketStream`] that sends a heartbeat message every interval,
+/// created by [`WebSocketStream::with_heartbeat`].
+///
+/// Messages are received and sent as with the wrapped stream. Heartbeats are
+/// sent while the stream is polled for the next message, so no separate task
+/// is required. A heartbeat that falls due while a message sent frame by frame
+/// has not been finished is skipped, as is every heartbeat once the closing
+/// handshake has begun.
+pub struct Heartbeat<T, F> {
+    /// The wrapped stream.
+    stream: WebSocketStream<T>,
+    /// Time between heartbeats.
+    period: Duration,
+    /// Timer for the next heartbeat. Created when first polled, since creating
+    /// it requires a runtime.
+    interval: Option<Interval>,
+    /// Creates the heartbeat messages.
+    message: F,
+}
+
+impl<T, F> Heartbeat<T, F> {
+    /// Wraps a stream to send heartbeats on.
+    pub(super) fn new(stream: WebSocketStream<T>, period: Duration, message: F) -> Self {
+        Self {
+            stream,
+            period,
+            interval: None,
+            message,
+        }
+    }
+
+    /// Returns a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &WebSocketStream<T> {
+        &self.stream
+    }
+
+    /// Returns a mutable reference to the wrapped stream.
+    pub fn get_mut(&mut self) -> &mut WebSocketStream<T> {
+        &mut self.stream
+    }
+
+    /// Consumes the `Heartbeat`, returning the wrapped stream.
+    pub fn into_inner(self) -> WebSocketStream<T> {
+        self.stream
+    }
+}
+
+impl<T, F> fmt::Debug for Heartbeat<T, F>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Heartbeat")
+            .field("stream", &self.stream)
+            .field("period", &self.period)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, F> Stream for Heartbeat<T, F>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+    F: FnMut() -> Message + Unpin,
+{
+    type Item = Result<Message, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let period = this.period;
+        let interval = this.interval.get_or_insert_with(|| {
+            let mut interval = interval_at(Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+
+        // The heartbeat is queued here and flushed while polling the stream.
+        // Polling until the timer is pending registers the waker for the next
+        // heartbeat.
+        while interval.poll_tick(cx).is_ready() {
+            if this.stream.state == StreamState::Active && !this.stream.writing_message {
+                let message = (this.message)();
+                Pin::new(&mut this.stream).start_send(message)?;
+            }
+        }
+
+        Pin::new(&mut this.stream).poll_next(cx)
+    }
+}
+
+impl<T, F> Sink<Message> for Heartbeat<T, F>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+    F: Unpin,
+{
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        Pin::new(&mut self.get_mut().stream).start_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_close(cx)
+    }
+}
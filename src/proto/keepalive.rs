@@ -0,0 +1,91 @@
+//! Timer state for sending keepalive pings on idle connections and detecting
+//! unresponsive remotes.
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use tokio::time::{Instant, Sleep, sleep};
+
+use super::types::{Frame, OpCode};
+use crate::Error;
+
+/// Keepalive state of a [`WebSocketStream`].
+///
+/// [`WebSocketStream`]: super::WebSocketStream
+#[derive(Debug)]
+pub(super) struct Keepalive {
+    /// Time without receiving frames after which a ping is sent.
+    interval: Duration,
+    /// Time to wait for a response after sending a ping.
+    timeout: Duration,
+    /// Timer for the next ping or the response timeout. Created when first
+    /// polled, since creating it requires a runtime.
+    sleep: Option<Pin<Box<Sleep>>>,
+    /// Token sent in the payload of the last ping, if it has not been answered
+    /// yet.
+    pending: Option<u32>,
+    /// Token to send in the payload of the next ping.
+    next_token: u32,
+}
+
+impl Keepalive {
+    /// Creates a new keepalive state.
+    pub(super) fn new(interval: Duration, timeout: Duration) -> Self {
+        Self {
+            interval,
+            timeout,
+            sleep: None,
+            pending: None,
+            next_token: 0,
+        }
+    }
+
+    /// Polls the timer and returns the payload of a ping to send once the
+    /// connection has been idle for the interval.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Error::KeepaliveTimeout`] if the previous ping was
+    /// not answered within the timeout.
+    pub(super) fn poll_ping(&mut self, cx: &mut Context<'_>) -> Poll<Result<[u8; 4], Error>> {
+        let interval = self.interval;
+        let timer = self.sleep.get_or_insert_with(|| Box::pin(sleep(interval)));
+
+        ready!(timer.as_mut().poll(cx));
+
+        if self.pending.is_some() {
+            return Poll::Ready(Err(Error::KeepaliveTimeout));
+        }
+
+        let token = self.next_token;
+        self.next_token = self.next_token.wrapping_add(1);
+        self.pending = Some(token);
+
+        timer.as_mut().reset(Instant::now() + self.timeout);
+        // Register the waker for the timeout
+        _ = timer.as_mut().poll(cx);
+
+        Poll::Ready(Ok(token.to_be_bytes()))
+    }
+
+    /// Records that a frame was received from the remote, which shows that the
+    /// connection is alive. Pongs only count if they answer the last ping.
+    pub(super) fn received(&mut self, frame: &Frame) {
+        if frame.opcode == OpCode::Pong
+            && self
+                .pending
+                .is_some_and(|token| *frame.payload != token.to_be_bytes())
+        {
+            return;
+        }
+
+        self.pending = None;
+
+        if let Some(timer) = &mut self.sleep {
+            timer.as_mut().reset(Instant::now() + self.interval);
+        }
+    }
+}
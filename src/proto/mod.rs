@@ -5,11 +5,29 @@
 pub(crate) use self::types::Role;
 pub use self::{
     error::ProtocolError,
+    reader::MessageReader,
+    split::{ReuniteError, SplitSink, SplitStream},
     stream::WebSocketStream,
-    types::{CloseCode, Config, Limits, Message, Payload},
+    types::{CloseCode, Config, Frame, Limits, Message, Payload},
+};
+#[cfg(any(feature = "client", feature = "server"))]
+pub use self::{
+    heartbeat::Heartbeat,
+    observer::Observer,
+    writer::{MessageKind, MessageWriter},
 };
 
 mod codec;
 mod error;
+#[cfg(any(feature = "client", feature = "server"))]
+mod heartbeat;
+#[cfg(any(feature = "client", feature = "server"))]
+mod keepalive;
+#[cfg(any(feature = "client", feature = "server"))]
+mod observer;
+mod reader;
+mod split;
 mod stream;
 mod types;
+#[cfg(any(feature = "client", feature = "server"))]
+mod writer;
@@ -0,0 +1,45 @@
+//! Hooks for observing the handshake and the frames of a [`WebSocketStream`],
+//! e.g. to collect metrics.
+//!
+//! [`WebSocketStream`]: super::WebSocketStream
+use std::fmt;
+
+use super::Frame;
+use crate::Error;
+
+/// Receives events of a WebSocket connection, installed via
+/// `ClientBuilder::observer` or `ServerBuilder::observer`.
+///
+/// All methods do nothing by default, so implementations only have to
+/// override the events they are interested in. They are called synchronously
+/// while the stream is polled and should therefore return quickly, e.g. by
+/// incrementing counters.
+///
+/// Messages are not reported separately. Their number can be derived from the
+/// frames, since every message ends with a [final] data frame or consists of
+/// a single control frame.
+///
+/// [final]: Frame::is_final
+pub trait Observer: fmt::Debug + Send + Sync {
+    /// Called once the handshake completed, or failed with an error.
+    fn handshake(&self, result: Result<(), &Error>) {
+        let _ = result;
+    }
+
+    /// Called when a frame is queued for sending, including pongs and close
+    /// frames sent automatically. The payload is not masked yet.
+    fn frame_sent(&self, frame: &Frame) {
+        let _ = frame;
+    }
+
+    /// Called when a valid frame was received.
+    fn frame_received(&self, frame: &Frame) {
+        let _ = frame;
+    }
+
+    /// Called when the connection fails because the remote violated the
+    /// protocol or exceeded a limit.
+    fn protocol_error(&self, error: &Error) {
+        let _ = error;
+    }
+}
@@ -0,0 +1,113 @@
+//! [`AsyncRead`] adapter that streams the payload of a single message as its
+//! frames arrive.
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use bytes::{Buf, Bytes};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::{WebSocketStream, types::OpCode};
+
+/// Reads the payload of the next data message of a [`WebSocketStream`] frame
+/// by frame, created by [`WebSocketStream::message_reader`].
+///
+/// Only a single frame is buffered at a time, so the memory used is bounded by
+/// the size of the largest frame rather than that of the message. Control
+/// frames received in between are handled as usual, e.g. pings are answered
+/// automatically, but they are not returned. The reader reaches EOF once the
+/// final frame of the message has been read or the stream is closed.
+///
+/// If the reader is dropped before the end of the message, the unread payload
+/// of the current frame is discarded. The remaining frames of the message can
+/// be read with a new reader or are returned as a binary message by the
+/// stream.
+#[derive(Debug)]
+pub struct MessageReader<'a, T> {
+    /// The stream to read frames from.
+    stream: &'a mut WebSocketStream<T>,
+    /// Payload of the current frame that has not been read yet.
+    payload: Bytes,
+    /// Opcode of the message being read, once its first frame was received.
+    opcode: Option<OpCode>,
+    /// Whether the final frame of the message has been received.
+    finished: bool,
+}
+
+impl<'a, T> MessageReader<'a, T> {
+    /// Creates a reader for the next message of a stream.
+    pub(super) fn new(stream: &'a mut WebSocketStream<T>) -> Self {
+        Self {
+            stream,
+            payload: Bytes::new(),
+            opcode: None,
+            finished: false,
+        }
+    }
+
+    /// Returns whether the message being read is a text message, once its
+    /// first frame has been received.
+    ///
+    /// The payload of text messages is validated to be UTF-8 as it is read.
+    /// For the remainder of a message that a previous reader did not finish,
+    /// this returns `false`.
+    pub fn is_text(&self) -> Option<bool> {
+        self.opcode.map(|opcode| opcode == OpCode::Text)
+    }
+}
+
+impl<T> AsyncRead for MessageReader<'_, T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        while this.payload.is_empty() {
+            if this.finished {
+                return Poll::Ready(Ok(()));
+            }
+
+            let Some(frame) = ready!(Pin::new(&mut *this.stream).poll_next_frame(cx)) else {
+                if this.opcode.is_some() {
+                    return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+                }
+
+                return Poll::Ready(Ok(()));
+            };
+            let frame = frame.map_err(io::Error::other)?;
+
+            if frame.opcode.is_control() {
+                continue;
+            }
+
+            // A continuation frame at the start belongs to a message that a
+            // previous reader did not finish
+            this.opcode.get_or_insert(frame.opcode);
+            this.finished = frame.is_final;
+
+            // Let the stream assemble the remaining frames of the message if
+            // this reader is dropped early. They might start in the middle of
+            // a UTF-8 character, so they are never marked as text.
+            this.stream.partial_opcode = if frame.is_final {
+                OpCode::Continuation
+            } else {
+                OpCode::Binary
+            };
+
+            this.payload = frame.payload.into();
+        }
+
+        let n = this.payload.len().min(buf.remaining());
+        buf.put_slice(&this.payload[..n]);
+        this.payload.advance(n);
+
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,159 @@
+//! Owned read and write halves of a [`WebSocketStream`] that can be used from
+//! separate tasks.
+//!
+//! Both halves share the stream through a mutex that is only locked for the
+//! duration of a single poll and never across an `.await`. Pongs and close
+//! acknowledgements queued while reading are flushed by the read half, which
+//! reuses the waker of a concurrently flushing write half, so that neither
+//! half's task is left without a wakeup.
+use std::{
+    fmt,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::{Message, WebSocketStream};
+use crate::Error;
+
+/// Locks the stream shared by the halves.
+///
+/// A panic while the lock is held leaves the stream in a state that is no
+/// worse than a panic in a poll method of an unsplit stream, so poisoning is
+/// ignored.
+fn lock<T>(stream: &Mutex<WebSocketStream<T>>) -> MutexGuard<'_, WebSocketStream<T>> {
+    stream.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Splits a stream into its read and write halves.
+pub(super) fn split<T>(stream: WebSocketStream<T>) -> (SplitSink<T>, SplitStream<T>) {
+    let stream = Arc::new(Mutex::new(stream));
+
+    (
+        SplitSink {
+            stream: stream.clone(),
+        },
+        SplitStream { stream },
+    )
+}
+
+/// Reunites two halves if they originate from the same stream.
+fn reunite<T>(
+    sink: SplitSink<T>,
+    stream: SplitStream<T>,
+) -> Result<WebSocketStream<T>, ReuniteError<T>> {
+    if !Arc::ptr_eq(&sink.stream, &stream.stream) {
+        return Err(ReuniteError(sink, stream));
+    }
+
+    drop(stream);
+
+    // Both halves have been consumed, so this is the last reference
+    let Ok(stream) = Arc::try_unwrap(sink.stream) else {
+        unreachable!("both halves were reunited");
+    };
+
+    Ok(stream.into_inner().unwrap_or_else(PoisonError::into_inner))
+}
+
+/// The write half of a [`WebSocketStream`], created by
+/// [`WebSocketStream::split`].
+///
+/// It implements [`futures_sink::Sink`] for sending messages.
+#[derive(Debug)]
+pub struct SplitSink<T> {
+    /// The stream shared with the read half.
+    stream: Arc<Mutex<WebSocketStream<T>>>,
+}
+
+impl<T> SplitSink<T> {
+    /// Reunites this half with the read half to recover the original
+    /// [`WebSocketStream`].
+    ///
+    /// # Errors
+    ///
+    /// This method returns a [`ReuniteError`] containing both halves if they
+    /// were not split from the same stream.
+    pub fn reunite(self, other: SplitStream<T>) -> Result<WebSocketStream<T>, ReuniteError<T>> {
+        reunite(self, other)
+    }
+}
+
+impl<T> Sink<Message> for SplitSink<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut *lock(&self.stream)).poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        Pin::new(&mut *lock(&self.stream)).start_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut *lock(&self.stream)).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut *lock(&self.stream)).poll_close(cx)
+    }
+}
+
+/// The read half of a [`WebSocketStream`], created by
+/// [`WebSocketStream::split`].
+///
+/// It implements [`futures_core::Stream`] for receiving messages.
+#[derive(Debug)]
+pub struct SplitStream<T> {
+    /// The stream shared with the write half.
+    stream: Arc<Mutex<WebSocketStream<T>>>,
+}
+
+impl<T> SplitStream<T> {
+    /// Reunites this half with the write half to recover the original
+    /// [`WebSocketStream`].
+    ///
+    /// # Errors
+    ///
+    /// This method returns a [`ReuniteError`] containing both halves if they
+    /// were not split from the same stream.
+    pub fn reunite(self, other: SplitSink<T>) -> Result<WebSocketStream<T>, ReuniteError<T>> {
+        reunite(other, self)
+    }
+}
+
+impl<T> Stream for SplitStream<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    type Item = Result<Message, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut *lock(&self.stream)).poll_next(cx)
+    }
+}
+
+/// Error returned when reuniting two halves that were not split from the same
+/// [`WebSocketStream`]. It contains both halves.
+pub struct ReuniteError<T>(pub SplitSink<T>, pub SplitStream<T>);
+
+impl<T> fmt::Debug for ReuniteError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReuniteError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for ReuniteError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("tried to reunite halves that are not from the same stream")
+    }
+}
+
+impl<T> std::error::Error for ReuniteError<T> {}
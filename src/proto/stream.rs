@@ -9,6 +9,8 @@ use std::{
     pin::Pin,
     task::{Context, Poll, Waker, ready},
 };
+#[cfg(any(feature = "client", feature = "server"))]
+use std::{future::poll_fn, sync::Arc, time::Duration};
 
 use bytes::{Buf, BytesMut};
 use futures_core::Stream;
@@ -16,13 +18,17 @@ use futures_sink::Sink;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_util::{codec::FramedRead, io::poll_write_buf};
 
-#[cfg(any(feature = "client", feature = "server"))]
-use super::types::Role;
 use super::{
-    Config, Limits,
+    Config, Limits, MessageReader, SplitSink, SplitStream,
     codec::WebSocketProtocol,
+    split,
     types::{Frame, Message, OpCode, Payload, StreamState},
 };
+#[cfg(any(feature = "client", feature = "server"))]
+use super::{
+    Heartbeat, MessageKind, MessageWriter, Observer, ProtocolError, keepalive::Keepalive,
+    types::Role,
+};
 use crate::{CloseCode, Error};
 
 /// Helper struct for storing a frame header, the header size and payload.
@@ -56,6 +62,13 @@ impl EncodedFrame {
     fn len(&self) -> usize {
         self.header_len() + self.payload.len()
     }
+
+    /// Whether this is a ping or pong frame, which are sent ahead of queued
+    /// data frames.
+    fn is_prioritized(&self) -> bool {
+        let opcode = self.header[0] & 0xF;
+        opcode == u8::from(OpCode::Ping) || opcode == u8::from(OpCode::Pong)
+    }
 }
 
 /// Queued up frames that are being sent.
@@ -82,9 +95,26 @@ impl FrameQueue {
     }
 
     /// Queue a frame to be sent.
+    ///
+    /// Pings and pongs are queued before any data frames, but after the frame
+    /// that is currently being written and other pings and pongs, so that they
+    /// are not delayed by large messages. Close frames are never moved
+    /// forward, since no data frames may follow them.
     fn push(&mut self, item: EncodedFrame) {
         self.pending_bytes += item.len();
-        self.queue.push_back(item);
+
+        if item.is_prioritized() {
+            let start = usize::from(self.bytes_written > 0);
+            let index = self
+                .queue
+                .iter()
+                .skip(start)
+                .position(|frame| !frame.is_prioritized())
+                .map_or(self.queue.len(), |index| index + start);
+            self.queue.insert(index, item);
+        } else {
+            self.queue.push_back(item);
+        }
     }
 }
 
@@ -186,15 +216,15 @@ pub struct WebSocketStream<T> {
     inner: FramedRead<T, WebSocketProtocol>,
 
     /// Configuration for the stream.
-    config: Config,
+    pub(super) config: Config,
 
     /// The [`StreamState`] of the current stream.
-    state: StreamState,
+    pub(super) state: StreamState,
 
     /// Payload of the full message that is being assembled.
     partial_payload: BytesMut,
     /// Opcode of the full message that is being assembled.
-    partial_opcode: OpCode,
+    pub(super) partial_opcode: OpCode,
 
     /// Buffer that outgoing frame headers are formatted into.
     header_buf: [u8; 14],
@@ -205,6 +235,26 @@ pub struct WebSocketStream<T> {
     /// Waker used for currently actively polling
     /// [`WebSocketStream::poll_flush`] until completion.
     flushing_waker: Option<Waker>,
+
+    /// Subprotocol negotiated during the handshake.
+    subprotocol: Option<String>,
+
+    /// Whether received pings are answered automatically.
+    auto_pong: bool,
+
+    /// Keepalive state, if keepalive pings are enabled.
+    #[cfg(any(feature = "client", feature = "server"))]
+    keepalive: Option<Keepalive>,
+
+    /// Whether frames of a message sent by a [`MessageWriter`] or via
+    /// [`WebSocketStream::send_frame`] have been queued, but not its final
+    /// frame.
+    #[cfg(any(feature = "client", feature = "server"))]
+    pub(super) writing_message: bool,
+
+    /// Observer notified of frames and protocol errors.
+    #[cfg(any(feature = "client", feature = "server"))]
+    observer: Option<Arc<dyn Observer>>,
 }
 
 impl<T> WebSocketStream<T>
@@ -215,7 +265,11 @@ where
     #[cfg(any(feature = "client", feature = "server"))]
     pub(crate) fn from_raw_stream(stream: T, role: Role, config: Config, limits: Limits) -> Self {
         Self {
-            inner: FramedRead::new(stream, WebSocketProtocol::new(role, limits)),
+            inner: FramedRead::with_capacity(
+                stream,
+                WebSocketProtocol::new(role, limits),
+                config.read_buffer_size,
+            ),
             config,
             state: StreamState::Active,
             partial_payload: BytesMut::new(),
@@ -223,6 +277,13 @@ where
             header_buf: [0; 14],
             frame_queue: FrameQueue::new(),
             flushing_waker: None,
+            subprotocol: None,
+            auto_pong: true,
+            keepalive: config
+                .keepalive
+                .map(|(interval, timeout)| Keepalive::new(interval, timeout)),
+            writing_message: false,
+            observer: None,
         }
     }
 
@@ -230,11 +291,14 @@ where
     /// allows for reusing the internal buffer of the [`FramedRead`] object.
     #[cfg(any(feature = "client", feature = "server"))]
     pub(crate) fn from_framed<U>(
-        framed: FramedRead<T, U>,
+        mut framed: FramedRead<T, U>,
         role: Role,
         config: Config,
         limits: Limits,
     ) -> Self {
+        let buffer = framed.read_buffer_mut();
+        buffer.reserve(config.read_buffer_size.saturating_sub(buffer.len()));
+
         Self {
             inner: framed.map_decoder(|_| WebSocketProtocol::new(role, limits)),
             config,
@@ -244,9 +308,22 @@ where
             header_buf: [0; 14],
             frame_queue: FrameQueue::new(),
             flushing_waker: None,
+            subprotocol: None,
+            auto_pong: true,
+            keepalive: config
+                .keepalive
+                .map(|(interval, timeout)| Keepalive::new(interval, timeout)),
+            writing_message: false,
+            observer: None,
         }
     }
 
+    /// Sets the observer notified of frames and protocol errors.
+    #[cfg(any(feature = "client", feature = "server"))]
+    pub(crate) fn set_observer(&mut self, observer: Option<Arc<dyn Observer>>) {
+        self.observer = observer;
+    }
+
     /// Returns a reference to the underlying I/O stream wrapped by this stream.
     ///
     /// Care should be taken not to tamper with the stream of data to avoid
@@ -264,6 +341,27 @@ where
         self.inner.get_mut()
     }
 
+    /// Sets the subprotocol negotiated during the handshake.
+    #[cfg(any(feature = "client", feature = "server"))]
+    pub(crate) fn set_subprotocol(&mut self, subprotocol: Option<String>) {
+        self.subprotocol = subprotocol;
+    }
+
+    /// Returns the subprotocol negotiated during the handshake, if any.
+    pub fn subprotocol(&self) -> Option<&str> {
+        self.subprotocol.as_deref()
+    }
+
+    /// Sets whether received pings are answered with pongs automatically. The
+    /// default is `true`.
+    ///
+    /// Received pings are returned as messages either way. If automatic pongs
+    /// are disabled, the pings must be answered manually via
+    /// [`Message::pong`] to comply with the protocol.
+    pub fn set_auto_pong(&mut self, auto_pong: bool) {
+        self.auto_pong = auto_pong;
+    }
+
     /// Returns a reference to the inner websocket limits.
     pub fn limits(&self) -> &Limits {
         &self.inner.decoder().limits
@@ -275,10 +373,255 @@ where
     }
 
     /// Consumes the `WebSocketStream`, returning its underlying I/O stream.
+    ///
+    /// Any data that has already been read from the I/O stream, but not yet
+    /// been parsed into frames, is discarded. Use
+    /// [`WebSocketStream::into_parts`] to retrieve it as well.
     pub fn into_inner(self) -> T {
         self.inner.into_inner()
     }
 
+    /// Consumes the `WebSocketStream`, returning its underlying I/O stream and
+    /// the data that has already been read from it, but not yet been parsed
+    /// into frames.
+    ///
+    /// Frames that are queued for sending and partially received messages are
+    /// discarded.
+    pub fn into_parts(self) -> (T, BytesMut) {
+        let parts = self.inner.into_parts();
+
+        (parts.io, parts.read_buf)
+    }
+
+    /// Returns a reader for the payload of the next data message, which is
+    /// read frame by frame as it arrives instead of being buffered in full.
+    ///
+    /// See [`MessageReader`] for details.
+    pub fn message_reader(&mut self) -> MessageReader<'_, T> {
+        MessageReader::new(self)
+    }
+
+    /// Returns a writer that sends a new message of the given kind
+    /// incrementally, without buffering it in full.
+    ///
+    /// See [`MessageWriter`] for details.
+    #[cfg(any(feature = "client", feature = "server"))]
+    pub fn message_writer(&mut self, kind: MessageKind) -> MessageWriter<'_, T> {
+        MessageWriter::new(self, kind)
+    }
+
+    /// Wraps the stream to send the message created by `message` every
+    /// `period` while it is polled for messages, e.g. for heartbeats expected
+    /// by an application protocol.
+    ///
+    /// Unlike [keepalive pings], the remote is not expected to respond. See
+    /// [`Heartbeat`] for details.
+    ///
+    /// [keepalive pings]: Config::keepalive
+    #[cfg(any(feature = "client", feature = "server"))]
+    pub fn with_heartbeat<F>(self, period: Duration, message: F) -> Heartbeat<T, F>
+    where
+        F: FnMut() -> Message,
+    {
+        Heartbeat::new(self, period, message)
+    }
+
+    /// Receives the next message, giving up if none is received within
+    /// `timeout`.
+    ///
+    /// Returns `Ok(None)` once the stream has ended, as described for its
+    /// [`Stream`] implementation. If the timeout expires, [`Error::Timeout`] is
+    /// returned and the stream remains usable: frames that were partially
+    /// received are kept and completed by the next call.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Error::Timeout`] if the timeout expires and any
+    /// error returned by the stream otherwise.
+    #[cfg(any(feature = "client", feature = "server"))]
+    pub async fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<Message>, Error> {
+        let mut this = Pin::new(self);
+
+        tokio::time::timeout(timeout, poll_fn(|cx| this.as_mut().poll_next(cx)))
+            .await
+            .map_err(|_| Error::Timeout)?
+            .transpose()
+    }
+
+    /// Receives the next frame without assembling frames into messages.
+    ///
+    /// Frames are validated as usual, received pings are answered unless
+    /// [automatic pongs] are disabled and received close frames complete the
+    /// closing handshake. The remaining frames of a message whose first frame
+    /// was received via this method are returned by the stream as a binary
+    /// message.
+    ///
+    /// [automatic pongs]: WebSocketStream::set_auto_pong
+    #[cfg(any(feature = "client", feature = "server"))]
+    pub async fn next_frame(&mut self) -> Option<Result<Frame, Error>> {
+        let mut this = Pin::new(self);
+        let frame = poll_fn(|cx| this.as_mut().poll_next_frame(cx)).await;
+
+        if let Some(Ok(frame)) = &frame
+            && !frame.opcode.is_control()
+        {
+            // Frames of the message might start in the middle of a UTF-8
+            // character, so they are never assembled as text
+            this.partial_opcode = if frame.is_final {
+                OpCode::Continuation
+            } else {
+                OpCode::Binary
+            };
+        }
+
+        frame
+    }
+
+    /// Sends a single frame and flushes the stream.
+    ///
+    /// The frame is masked as required by the role of the stream, but is not
+    /// split according to the configured [frame size]. The caller is
+    /// responsible for sending a valid sequence of frames, i.e. continuation
+    /// frames only after a frame that is not final and text messages that are
+    /// valid UTF-8 once assembled.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an [`Error`] if the stream is already closed, a
+    /// frame that starts a new message is sent before the previous message
+    /// was finished or writing to the stream fails.
+    ///
+    /// [frame size]: Config::frame_size
+    #[cfg(any(feature = "client", feature = "server"))]
+    pub async fn send_frame(&mut self, frame: Frame) -> Result<(), Error> {
+        let mut this = Pin::new(self);
+
+        poll_fn(|cx| this.as_mut().poll_ready(cx)).await?;
+        this.check_frame(&frame)?;
+
+        this.queue_frame(frame);
+        poll_fn(|cx| this.as_mut().poll_flush(cx)).await
+    }
+
+    /// Sends a single frame with an explicit mask key, or unmasked, and
+    /// flushes the stream.
+    ///
+    /// Unlike [`WebSocketStream::send_frame`], the frame is not masked
+    /// according to the role of the stream. If a mask key is given, it is
+    /// written to the frame header as-is and the payload must already be
+    /// masked with it, so that relays can forward masked payloads without
+    /// unmasking and masking them again.
+    ///
+    /// **Warning:** Clients must mask all frames and servers must not mask
+    /// any, otherwise the remote fails the connection. A mask key that does
+    /// not match the payload silently corrupts it. Only use this with frames
+    /// that were masked correctly for the role of this stream.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an [`Error`] under the same conditions as
+    /// [`WebSocketStream::send_frame`].
+    #[cfg(any(feature = "client", feature = "server"))]
+    pub async fn send_frame_with_mask(
+        &mut self,
+        frame: Frame,
+        mask: Option<[u8; 4]>,
+    ) -> Result<(), Error> {
+        let mut this = Pin::new(self);
+
+        poll_fn(|cx| this.as_mut().poll_ready(cx)).await?;
+        this.check_frame(&frame)?;
+
+        this.queue_frame_with_mask(frame, mask);
+        poll_fn(|cx| this.as_mut().poll_flush(cx)).await
+    }
+
+    /// Checks whether a frame can be sent by the caller and tracks whether a
+    /// data message is in progress.
+    #[cfg(any(feature = "client", feature = "server"))]
+    fn check_frame(&mut self, frame: &Frame) -> Result<(), Error> {
+        if self.state != StreamState::Active {
+            return Err(Error::AlreadyClosed);
+        }
+
+        if !frame.opcode.is_control() {
+            if self.writing_message && frame.opcode != OpCode::Continuation {
+                return Err(Error::FragmentationInProgress);
+            }
+
+            self.writing_message = !frame.is_final;
+        }
+
+        Ok(())
+    }
+
+    /// Splits the stream into a write half and a read half that can be used
+    /// concurrently from separate tasks.
+    ///
+    /// Unlike splitting via [`futures_util`], the halves can be reunited via
+    /// [`SplitSink::reunite`] or [`SplitStream::reunite`]. Pings and close
+    /// frames received by the read half are still answered automatically.
+    ///
+    /// [`futures_util`]: https://docs.rs/futures-util
+    pub fn split(self) -> (SplitSink<T>, SplitStream<T>) {
+        split::split(self)
+    }
+
+    /// Closes the stream with a status code and reason, completing the
+    /// closing handshake.
+    ///
+    /// This sends a close frame and then waits for the remote to acknowledge
+    /// it, discarding any messages received in the meantime. If the remote
+    /// does not acknowledge the close frame within the [close timeout], the
+    /// underlying I/O is shut down regardless. Messages can no longer be sent
+    /// afterwards.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an [`Error`] if the `code` is reserved, the `reason`
+    /// exceeds 123 bytes, the stream is already closed or writing to or
+    /// reading from the stream fails.
+    ///
+    /// [close timeout]: Config::close_timeout
+    #[cfg(any(feature = "client", feature = "server"))]
+    pub async fn close_with(&mut self, code: CloseCode, reason: &str) -> Result<(), Error> {
+        if code.is_reserved() {
+            return Err(Error::Protocol(ProtocolError::InvalidCloseCode));
+        }
+
+        // The close code takes up 2 of the 125 bytes available in control frames
+        if reason.len() > 123 {
+            return Err(Error::PayloadTooLong {
+                len: reason.len(),
+                max_len: 123,
+            });
+        }
+
+        let mut this = Pin::new(self);
+
+        poll_fn(|cx| this.as_mut().poll_ready(cx)).await?;
+        this.as_mut()
+            .start_send(Message::close(Some(code), reason))?;
+        poll_fn(|cx| this.as_mut().poll_flush(cx)).await?;
+
+        let timeout = this.config.close_timeout;
+        let acknowledged = async {
+            while let Some(message) = poll_fn(|cx| this.as_mut().poll_next(cx)).await {
+                message?;
+            }
+
+            Ok::<_, Error>(())
+        };
+        if let Ok(result) = tokio::time::timeout(timeout, acknowledged).await {
+            result?;
+        }
+
+        poll_fn(|cx| this.as_mut().poll_flush(cx)).await?;
+        poll_fn(|cx| Pin::new(this.inner.get_mut()).poll_shutdown(cx)).await?;
+
+        Ok(())
+    }
+
     /// Attempt to pull out the next frame from the [`Framed`] this stream and
     /// from that update the stream's internal state.
     ///
@@ -286,7 +629,7 @@ where
     ///
     /// This method returns an [`Error`] if reading from the stream fails or a
     /// protocol violation is encountered.
-    fn poll_next_frame(
+    pub(super) fn poll_next_frame(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Frame, Error>>> {
@@ -301,6 +644,20 @@ where
             return Poll::Ready(None);
         }
 
+        #[cfg(any(feature = "client", feature = "server"))]
+        if self.state == StreamState::Active
+            && let Some(keepalive) = &mut self.keepalive
+            && let Poll::Ready(result) = keepalive.poll_ping(cx)
+        {
+            match result {
+                Ok(token) => self.queue_frame(Message::ping(token.to_vec()).into()),
+                Err(e) => {
+                    self.state = StreamState::CloseAcknowledged;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+
         // If there are pending items, try to flush the sink.
         // Futures only store a single waker. If we use poll_flush(cx) here, the stored
         // waker (i.e. usually that of the write task) is replaced with our waker (i.e.
@@ -317,28 +674,31 @@ where
         let frame = match ready!(Pin::new(&mut self.inner).poll_next(cx)) {
             Some(Ok(frame)) => frame,
             Some(Err(e)) => {
-                if matches!(e, Error::Io(_)) || self.state == StreamState::ClosedByUs {
+                if matches!(e, Error::Io(_)) {
                     self.state = StreamState::CloseAcknowledged;
                 } else {
-                    self.state = StreamState::ClosedByPeer;
-
-                    match &e {
-                        Error::Protocol(e) => self.queue_frame(Frame::from(e)),
-                        Error::PayloadTooLong { max_len, .. } => self.queue_frame(
-                            Message::close(
-                                Some(CloseCode::MESSAGE_TOO_BIG),
-                                &format!("max length: {max_len}"),
-                            )
-                            .into(),
-                        ),
-                        _ => {}
-                    }
+                    self.fail_connection(&e);
                 }
                 return Poll::Ready(Some(Err(e)));
             }
-            None => return Poll::Ready(None),
+            None => {
+                // The close handshake is incomplete, so the remote went away without
+                // closing the connection properly
+                self.state = StreamState::CloseAcknowledged;
+                return Poll::Ready(Some(Err(Error::Io(io::ErrorKind::UnexpectedEof.into()))));
+            }
         };
 
+        #[cfg(any(feature = "client", feature = "server"))]
+        if let Some(keepalive) = &mut self.keepalive {
+            keepalive.received(&frame);
+        }
+
+        #[cfg(any(feature = "client", feature = "server"))]
+        if let Some(observer) = &self.observer {
+            observer.frame_received(&frame);
+        }
+
         match frame.opcode {
             OpCode::Close => match self.state {
                 StreamState::Active => {
@@ -356,7 +716,7 @@ where
                     self.state = StreamState::CloseAcknowledged;
                 }
             },
-            OpCode::Ping if self.state == StreamState::Active => {
+            OpCode::Ping if self.auto_pong && self.state == StreamState::Active => {
                 let mut frame = frame.clone();
                 frame.opcode = OpCode::Pong;
 
@@ -368,14 +728,42 @@ where
         Poll::Ready(Some(Ok(frame)))
     }
 
+    /// Fails the connection after receiving invalid data from the remote by
+    /// queueing a close frame with a status code matching the error, unless
+    /// the stream is already closing.
+    fn fail_connection(&mut self, e: &Error) {
+        #[cfg(any(feature = "client", feature = "server"))]
+        if let Some(observer) = &self.observer {
+            observer.protocol_error(e);
+        }
+
+        if self.state == StreamState::ClosedByUs {
+            self.state = StreamState::CloseAcknowledged;
+            return;
+        }
+
+        self.state = StreamState::ClosedByPeer;
+
+        match e {
+            Error::Protocol(e) => self.queue_frame(Frame::from(e)),
+            Error::PayloadTooLong { max_len, .. } | Error::FrameTooLong { max_len, .. } => self
+                .queue_frame(
+                    Message::close(
+                        Some(CloseCode::MESSAGE_TOO_BIG),
+                        &format!("max length: {max_len}"),
+                    )
+                    .into(),
+                ),
+            _ => {}
+        }
+    }
+
     /// Masks and queues a frame for sending when [`poll_flush`] gets called.
-    fn queue_frame(
+    pub(super) fn queue_frame(
         &mut self,
         #[cfg_attr(not(feature = "client"), allow(unused_mut))] mut frame: Frame,
     ) {
-        if frame.opcode == OpCode::Close && self.state != StreamState::ClosedByPeer {
-            self.state = StreamState::ClosedByUs;
-        }
+        self.frame_queued(&frame);
 
         #[cfg_attr(not(feature = "client"), allow(unused_variables))]
         let mask = frame.encode(&mut self.header_buf);
@@ -403,6 +791,39 @@ where
         self.frame_queue.push(item);
     }
 
+    /// Queues a frame with its payload unchanged, using an explicit mask key
+    /// or none instead of masking it according to the role.
+    #[cfg(any(feature = "client", feature = "server"))]
+    fn queue_frame_with_mask(&mut self, frame: Frame, mask: Option<[u8; 4]>) {
+        self.frame_queued(&frame);
+
+        let mask_slice = frame.encode(&mut self.header_buf);
+
+        if let Some(mask) = mask {
+            *mask_slice = mask;
+            self.header_buf[1] |= 1 << 7;
+        }
+
+        let item = EncodedFrame {
+            header: self.header_buf,
+            payload: frame.payload,
+        };
+        self.frame_queue.push(item);
+    }
+
+    /// Notifies the observer of a frame that is being queued and, for close
+    /// frames, marks the stream as closed by us unless the peer closed it.
+    fn frame_queued(&mut self, frame: &Frame) {
+        #[cfg(any(feature = "client", feature = "server"))]
+        if let Some(observer) = &self.observer {
+            observer.frame_sent(frame);
+        }
+
+        if frame.opcode == OpCode::Close && self.state != StreamState::ClosedByPeer {
+            self.state = StreamState::ClosedByUs;
+        }
+    }
+
     /// Sets the waker that is currently flushing to a new one and does nothing
     /// if the waker is the same.
     fn set_flushing_waker(&mut self, waker: &Waker) {
@@ -416,6 +837,43 @@ where
     }
 }
 
+/// Receives the next message, assembled from its frames.
+///
+/// Pings and pongs are returned as messages as well, even if pings are
+/// answered automatically, see [`WebSocketStream::set_auto_pong`]. A close
+/// message sent by the peer is returned once and answered automatically if
+/// the stream did not initiate the close handshake itself.
+///
+/// After the close handshake completes and the close message has been
+/// returned, the stream yields [`None`]. An error is returned if the peer
+/// violates the protocol, a limit is exceeded, the underlying I/O fails or it
+/// ends before the close handshake was completed, in which case it returns
+/// [`Error::Io`] with [`io::ErrorKind::UnexpectedEof`]. After an error, the
+/// stream attempts to send a matching close frame to the peer and yields
+/// [`None`] afterwards.
+///
+/// # Cancel safety
+///
+/// Receiving is cancel safe, e.g. when used in `tokio::select!`. All state
+/// of a partially received message, including bytes of frames that were read
+/// from the underlying I/O but not decoded yet, is kept in the stream rather
+/// than in the future returned by `StreamExt::next`, so dropping it and
+/// polling the stream again resumes where it left off.
+///
+/// # Backpressure
+///
+/// Messages are not read ahead: the underlying I/O is only read from while
+/// the stream is polled, and only until the next message is complete. Memory
+/// held for incoming data is therefore bounded by the message being received,
+/// see [`Limits::max_payload_len`], plus the bytes that arrived after it in
+/// the same read, which are limited by the capacity of the read buffer. A
+/// slow consumer applies backpressure to the peer through the transport,
+/// e.g. to its TCP send window.
+///
+/// This also means that control frames are only processed while polling:
+/// pings are not answered and close frames are not noticed while the
+/// consumer is busy. Keep polling the stream, e.g. by handing messages to a
+/// bounded channel, if the peer expects timely pongs.
 impl<T> Stream for WebSocketStream<T>
 where
     T: AsyncRead + AsyncWrite + Unpin,
@@ -439,7 +897,12 @@ where
                 self.partial_opcode = opcode;
                 self.partial_payload = BytesMut::from(payload);
             } else if len > max_len {
-                return Poll::Ready(Some(Err(Error::PayloadTooLong { len, max_len })));
+                // Fail as soon as the fragments received so far exceed the limit
+                let e = Error::PayloadTooLong { len, max_len };
+                self.partial_payload = BytesMut::new();
+                self.fail_connection(&e);
+
+                return Poll::Ready(Some(Err(e)));
             } else {
                 self.partial_payload.extend_from_slice(&payload);
             }
@@ -462,6 +925,19 @@ where
 // with a hefty performance penalty when sending large payloads, since this adds
 // a memmove from the payload to the buffer. We completely avoid that overhead
 // by storing messages in a deque.
+/// Sends messages, split into frames according to [`Config::frame_size`].
+///
+/// [`Sink::start_send`] only queues the frames of a message, masking them if
+/// the stream is a client. They are written to the underlying I/O by
+/// [`Sink::poll_flush`]. [`Sink::poll_ready`] flushes first once at least
+/// [`Config::flush_threshold`] bytes are queued, which applies backpressure
+/// to callers that only feed messages without flushing.
+///
+/// [`Sink::poll_close`] starts the close handshake with
+/// [`CloseCode::NORMAL_CLOSURE`] unless a close message was sent or received
+/// already, and waits for the peer to acknowledge it, discarding messages
+/// received in the meantime, before shutting down the underlying I/O. Once
+/// the close handshake has begun, sending fails with [`Error::AlreadyClosed`].
 impl<T> Sink<Message> for WebSocketStream<T>
 where
     T: AsyncRead + AsyncWrite + Unpin,
@@ -483,6 +959,13 @@ where
             return Err(Error::AlreadyClosed);
         }
 
+        // The frames of a data message must not be interleaved with those
+        // of another one
+        #[cfg(any(feature = "client", feature = "server"))]
+        if self.writing_message && !item.opcode.is_control() {
+            return Err(Error::FragmentationInProgress);
+        }
+
         if item.opcode.is_control() || item.payload.len() <= self.config.frame_size {
             let frame: Frame = item.into();
             self.queue_frame(frame);
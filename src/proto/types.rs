@@ -1,5 +1,5 @@
 //! Types required for the WebSocket protocol implementation.
-use std::{fmt, mem::replace, num::NonZeroU16, ops::Deref};
+use std::{fmt, mem::replace, num::NonZeroU16, ops::Deref, time::Duration};
 
 use bytes::{BufMut, Bytes, BytesMut};
 
@@ -64,6 +64,12 @@ impl From<OpCode> for u8 {
 }
 
 /// Close status code.
+///
+/// Codes can be converted from a [`u16`] via [`TryFrom`], which fails for codes
+/// that are neither defined by the protocol (1000-1015) nor available to
+/// libraries and applications (3000-4999).
+/// Codes that are reserved for local use, such as
+/// [`CloseCode::NO_STATUS_RECEIVED`], are rejected in received close frames.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct CloseCode(NonZeroU16);
 
@@ -130,6 +136,12 @@ impl CloseCode {
         Self::try_from_u16(code).unwrap()
     }
 
+    /// Returns the numeric value of the close code.
+    #[must_use]
+    pub const fn as_u16(self) -> u16 {
+        self.0.get()
+    }
+
     /// Whether the close code is reserved and cannot be sent over the wire.
     #[must_use]
     pub fn is_reserved(self) -> bool {
@@ -177,6 +189,10 @@ pub struct Payload {
 }
 
 impl Payload {
+    /// Number of bytes of the payload shown by its [`Debug`](fmt::Debug)
+    /// implementation.
+    pub const DEBUG_PREVIEW_LEN: usize = 32;
+
     /// Creates a new shared `Payload` from a static slice.
     const fn from_static(bytes: &'static [u8]) -> Self {
         Self {
@@ -218,8 +234,21 @@ impl Deref for Payload {
 }
 
 impl fmt::Debug for Payload {
+    /// Formats the payload, truncated to the first
+    /// [`DEBUG_PREVIEW_LEN`](Payload::DEBUG_PREVIEW_LEN) bytes followed by its
+    /// length if it is longer, so that large or sensitive payloads do not end
+    /// up in logs entirely.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_tuple("Payload").field(&self.data).finish()
+        let mut tuple = f.debug_tuple("Payload");
+
+        if self.data.len() > Self::DEBUG_PREVIEW_LEN {
+            tuple
+                .field(&self.data.slice(..Self::DEBUG_PREVIEW_LEN))
+                .field(&format_args!("{} bytes", self.data.len()))
+                .finish()
+        } else {
+            tuple.field(&self.data).finish()
+        }
     }
 }
 
@@ -306,6 +335,27 @@ pub struct Message {
     pub(super) payload: Payload,
 }
 
+impl fmt::Display for Message {
+    /// Formats a summary of the message that omits the payload, except for the
+    /// close code and reason of close messages, e.g. `Text(5 bytes)` or
+    /// `Close(1000, "bye")`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((code, reason)) = self.as_close() {
+            return write!(f, "Close({}, {reason:?})", u16::from(code));
+        }
+
+        let kind = match self.opcode {
+            OpCode::Text => "Text",
+            OpCode::Binary => "Binary",
+            OpCode::Ping => "Ping",
+            OpCode::Pong => "Pong",
+            OpCode::Continuation | OpCode::Close => unreachable!("not a message opcode"),
+        };
+
+        write!(f, "{kind}({} bytes)", self.payload.len())
+    }
+}
+
 impl Message {
     /// Create a new text message. The payload contents must be valid UTF-8.
     #[must_use]
@@ -423,6 +473,42 @@ impl Message {
         &self.payload
     }
 
+    /// Returns the message payload as [`Bytes`] and consumes the message,
+    /// regardless of type. This is zero-cost.
+    #[must_use]
+    pub fn into_bytes(self) -> Bytes {
+        self.payload.into()
+    }
+
+    /// Returns the message payload as a [`String`] and consumes the message if
+    /// it is a text message, otherwise the message is returned unchanged.
+    ///
+    /// This is zero-copy if the payload buffer is unique, otherwise the payload
+    /// is copied.
+    ///
+    /// # Errors
+    ///
+    /// This method returns the message itself if it is not a text message.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic when the message was created via
+    /// [`Message::text`] with invalid UTF-8.
+    pub fn into_text(self) -> Result<String, Self> {
+        if self.opcode != OpCode::Text {
+            return Err(self);
+        }
+
+        assert!(
+            self.payload.utf8_validated || utf8::parse_str(&self.payload).is_ok(),
+            "called into_text on message created from payload with invalid utf-8"
+        );
+        let bytes = Vec::from(Bytes::from(self.payload));
+
+        // SAFETY: The payload was validated to be valid UTF-8 above
+        Ok(unsafe { String::from_utf8_unchecked(bytes) })
+    }
+
     /// Returns a reference to the message payload as a string if it is a text
     /// message.
     ///
@@ -444,6 +530,9 @@ impl Message {
 
     /// Returns the [`CloseCode`] and close reason if the message is a close
     /// message.
+    ///
+    /// A close message without a payload has no code, which is reported as
+    /// [`CloseCode::NO_STATUS_RECEIVED`] with an empty reason.
     pub fn as_close(&self) -> Option<(CloseCode, &str)> {
         (self.opcode == OpCode::Close).then(|| {
             let code = if self.payload.is_empty() {
@@ -519,6 +608,9 @@ impl Iterator for MessageFrames {
 pub struct Limits {
     /// The maximum allowed payload length. The default is 64 MiB.
     pub(super) max_payload_len: usize,
+    /// The maximum allowed payload length of a single frame. The default is
+    /// no limit.
+    pub(super) max_frame_len: usize,
 }
 
 impl Limits {
@@ -527,6 +619,7 @@ impl Limits {
     pub fn unlimited() -> Self {
         Self {
             max_payload_len: usize::MAX,
+            max_frame_len: usize::MAX,
         }
     }
 
@@ -544,12 +637,30 @@ impl Limits {
     pub fn set_max_payload_len(&mut self, size: Option<usize>) {
         self.max_payload_len = size.unwrap_or(usize::MAX);
     }
+
+    /// Sets the maximum allowed payload length of a single frame, independent
+    /// of the total length of messages. `None` equals no limit.
+    ///
+    /// The limit is checked as soon as a frame header has been received,
+    /// before the frame payload is buffered. The default is no limit.
+    #[must_use]
+    pub fn max_frame_len(mut self, size: Option<usize>) -> Self {
+        self.set_max_frame_len(size);
+
+        self
+    }
+
+    /// See [`max_frame_len`](Self::max_frame_len).
+    pub fn set_max_frame_len(&mut self, size: Option<usize>) {
+        self.max_frame_len = size.unwrap_or(usize::MAX);
+    }
 }
 
 impl Default for Limits {
     fn default() -> Self {
         Self {
             max_payload_len: 64 * 1024 * 1024,
+            max_frame_len: usize::MAX,
         }
     }
 }
@@ -568,11 +679,27 @@ pub struct Config {
     /// Threshold of queued up bytes after which the underlying I/O is flushed
     /// before the sink is declared ready. The default is 8 KiB.
     pub(super) flush_threshold: usize,
+    /// Time to wait for the remote to acknowledge a close frame sent via
+    /// [`WebSocketStream::close_with`]. The default is 5 seconds.
+    ///
+    /// [`WebSocketStream::close_with`]: super::WebSocketStream::close_with
+    pub(super) close_timeout: Duration,
+    /// Interval and timeout of keepalive pings, if enabled. Disabled by
+    /// default.
+    pub(super) keepalive: Option<(Duration, Duration)>,
+    /// Initial capacity of the buffer that data read from the underlying I/O
+    /// is stored in. The default is 8 KiB.
+    pub(super) read_buffer_size: usize,
 }
 
 impl Config {
     /// Set the frame payload size to split outgoing messages into.
     ///
+    /// Messages with larger payloads are sent as a frame with the message's
+    /// opcode followed by continuation frames, which the remote reassembles
+    /// into a single message. Control frames are never split. Use
+    /// [`usize::MAX`] to never split messages.
+    ///
     /// Consider decreasing this if the remote imposes a limit on the frame
     /// payload size. The default is 4MiB.
     ///
@@ -589,12 +716,65 @@ impl Config {
 
     /// Sets the threshold of queued up bytes after which the underlying I/O is
     /// flushed before the sink is declared ready. The default is 8 KiB.
+    ///
+    /// This bounds the memory used by messages that are sent faster than the
+    /// remote reads them: once the threshold is reached, [`Sink::poll_ready`]
+    /// flushes and is pending until enough of the queued frames have been
+    /// written to fall below it, which applies backpressure to e.g.
+    /// `SinkExt::feed`. Pongs and close frames sent in
+    /// response to received frames are queued regardless of the threshold.
+    ///
+    /// [`Sink::poll_ready`]: futures_sink::Sink::poll_ready
     #[must_use]
     pub fn flush_threshold(mut self, threshold: usize) -> Self {
         self.flush_threshold = threshold;
 
         self
     }
+
+    /// Sets the time to wait for the remote to acknowledge a close frame sent
+    /// via [`WebSocketStream::close_with`]. The default is 5 seconds.
+    ///
+    /// [`WebSocketStream::close_with`]: super::WebSocketStream::close_with
+    #[must_use]
+    pub fn close_timeout(mut self, timeout: Duration) -> Self {
+        self.close_timeout = timeout;
+
+        self
+    }
+
+    /// Enables keepalive pings, which are sent once no frame has been received
+    /// from the remote for `interval`. If the remote does not respond within
+    /// `timeout`, the stream fails with [`Error::KeepaliveTimeout`].
+    ///
+    /// Pings are sent and timeouts detected while the stream is being polled
+    /// for messages, so it must be polled continuously, e.g. via the read half
+    /// of a [split] stream. Keepalive pings are disabled by default.
+    ///
+    /// [`Error::KeepaliveTimeout`]: crate::Error::KeepaliveTimeout
+    /// [split]: super::WebSocketStream::split
+    #[must_use]
+    pub fn keepalive(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.keepalive = Some((interval, timeout));
+
+        self
+    }
+
+    /// Sets the initial capacity of the buffer that data read from the
+    /// underlying I/O is stored in. The default is 8 KiB.
+    ///
+    /// The buffer grows on demand: once a frame header has been read, enough
+    /// space is reserved to hold the entire frame, so the largest allocation is
+    /// bounded by [`Limits::max_frame_len`] and [`Limits::max_payload_len`]
+    /// rather than by this setting. Raising it avoids reallocations for remotes
+    /// that send many small frames in quick succession, lowering it saves
+    /// memory on mostly idle connections.
+    #[must_use]
+    pub fn read_buffer_size(mut self, size: usize) -> Self {
+        self.read_buffer_size = size;
+
+        self
+    }
 }
 
 impl Default for Config {
@@ -602,6 +782,9 @@ impl Default for Config {
         Self {
             frame_size: 4 * 1024 * 1024,
             flush_threshold: 8 * 1024,
+            close_timeout: Duration::from_secs(5),
+            keepalive: None,
+            read_buffer_size: 8 * 1024,
         }
     }
 }
@@ -630,29 +813,121 @@ pub(super) enum StreamState {
     CloseAcknowledged,
 }
 
-/// A frame of a WebSocket [`Message`].
+/// A single frame of a WebSocket [`Message`], sent and received via
+/// [`WebSocketStream::send_frame`] and [`WebSocketStream::next_frame`].
+///
+/// Data messages consist of a text or binary frame followed by any number of
+/// continuation frames, the last of which is final. Control frames are always
+/// final and can be created from a [`Message`].
+///
+/// [`WebSocketStream::send_frame`]: super::WebSocketStream::send_frame
+/// [`WebSocketStream::next_frame`]: super::WebSocketStream::next_frame
 #[derive(Clone, Debug)]
-pub(super) struct Frame {
+pub struct Frame {
     /// The [`OpCode`] of the frame.
-    pub opcode: OpCode,
+    pub(super) opcode: OpCode,
     /// Whether this is the last frame of a message.
-    pub is_final: bool,
+    pub(super) is_final: bool,
     /// The payload bytes of the frame.
-    pub payload: Payload,
+    pub(super) payload: Payload,
 }
 
 impl Frame {
     /// Default close frame.
     #[allow(clippy::declare_interior_mutable_const)]
-    pub const DEFAULT_CLOSE: Self = Self {
+    pub(super) const DEFAULT_CLOSE: Self = Self {
         opcode: OpCode::Close,
         is_final: true,
         payload: Payload::from_static(&CloseCode::NORMAL_CLOSURE.0.get().to_be_bytes()),
     };
 
+    /// Create a new text frame that starts a message. The payload of the
+    /// whole message must be valid UTF-8.
+    #[must_use]
+    pub fn text<P: Into<Payload>>(payload: P, is_final: bool) -> Self {
+        Self {
+            opcode: OpCode::Text,
+            is_final,
+            payload: payload.into(),
+        }
+    }
+
+    /// Create a new binary frame that starts a message.
+    #[must_use]
+    pub fn binary<P: Into<Payload>>(payload: P, is_final: bool) -> Self {
+        Self {
+            opcode: OpCode::Binary,
+            is_final,
+            payload: payload.into(),
+        }
+    }
+
+    /// Create a new continuation frame of a message.
+    #[must_use]
+    pub fn continuation<P: Into<Payload>>(payload: P, is_final: bool) -> Self {
+        Self {
+            opcode: OpCode::Continuation,
+            is_final,
+            payload: payload.into(),
+        }
+    }
+
+    /// Whether this is the last frame of a message.
+    #[must_use]
+    pub fn is_final(&self) -> bool {
+        self.is_final
+    }
+
+    /// Whether the frame is a text frame.
+    #[must_use]
+    pub fn is_text(&self) -> bool {
+        self.opcode == OpCode::Text
+    }
+
+    /// Whether the frame is a binary frame.
+    #[must_use]
+    pub fn is_binary(&self) -> bool {
+        self.opcode == OpCode::Binary
+    }
+
+    /// Whether the frame is a continuation frame.
+    #[must_use]
+    pub fn is_continuation(&self) -> bool {
+        self.opcode == OpCode::Continuation
+    }
+
+    /// Whether the frame is a close frame.
+    #[must_use]
+    pub fn is_close(&self) -> bool {
+        self.opcode == OpCode::Close
+    }
+
+    /// Whether the frame is a ping frame.
+    #[must_use]
+    pub fn is_ping(&self) -> bool {
+        self.opcode == OpCode::Ping
+    }
+
+    /// Whether the frame is a pong frame.
+    #[must_use]
+    pub fn is_pong(&self) -> bool {
+        self.opcode == OpCode::Pong
+    }
+
+    /// Returns the frame payload and consumes the frame, regardless of type.
+    #[must_use]
+    pub fn into_payload(self) -> Payload {
+        self.payload
+    }
+
+    /// Returns a reference to the frame payload, regardless of frame type.
+    pub fn as_payload(&self) -> &Payload {
+        &self.payload
+    }
+
     /// Encode the frame head into `out`, returning a subslice where the mask
     /// should be written to.
-    pub fn encode<'a>(&self, out: &'a mut [u8; 14]) -> &'a mut [u8; 4] {
+    pub(super) fn encode<'a>(&self, out: &'a mut [u8; 14]) -> &'a mut [u8; 4] {
         out[0] = (u8::from(self.is_final) << 7) | u8::from(self.opcode);
         let mask_slice = if u16::try_from(self.payload.len()).is_err() {
             out[1] = 127;
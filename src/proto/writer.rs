@@ -0,0 +1,174 @@
+//! [`AsyncWrite`] adapter that sends a single message incrementally as
+//! continuation frames.
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use futures_sink::Sink;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::{
+    CloseCode, Message, Payload, WebSocketStream,
+    types::{Frame, OpCode, StreamState},
+};
+use crate::{Error, utf8::Validator};
+
+/// The kind of data message sent by a [`MessageWriter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    /// A text message, its payload must be valid UTF-8.
+    Text,
+    /// A binary message.
+    Binary,
+}
+
+/// Sends a single data message on a [`WebSocketStream`] incrementally, created
+/// by [`WebSocketStream::message_writer`].
+///
+/// Each write is sent as a frame that is not final, split according to the
+/// configured [frame size]. The message is completed by sending an empty final
+/// frame when the writer is [shut down], which does not shut down the
+/// underlying I/O. For text messages, the written bytes are validated to be
+/// UTF-8 as they are written.
+///
+/// If the writer is dropped before it is shut down, the message cannot be
+/// completed anymore and the stream is closed with
+/// [`CloseCode::INTERNAL_SERVER_ERROR`] the next time it is flushed. If it is
+/// leaked instead, sending other data messages on the stream fails with
+/// [`Error::FragmentationInProgress`].
+///
+/// [frame size]: super::Config::frame_size
+/// [shut down]: tokio::io::AsyncWriteExt::shutdown
+#[derive(Debug)]
+pub struct MessageWriter<'a, T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    /// The stream to send frames on.
+    stream: &'a mut WebSocketStream<T>,
+    /// Opcode of the next frame to send.
+    opcode: OpCode,
+    /// UTF-8 validator for text messages.
+    validator: Option<Validator>,
+    /// Whether the final frame of the message has been queued.
+    finished: bool,
+}
+
+impl<'a, T> MessageWriter<'a, T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Creates a writer for a new message on a stream.
+    pub(super) fn new(stream: &'a mut WebSocketStream<T>, kind: MessageKind) -> Self {
+        let (opcode, validator) = match kind {
+            MessageKind::Text => (OpCode::Text, Some(Validator::new())),
+            MessageKind::Binary => (OpCode::Binary, None),
+        };
+
+        Self {
+            stream,
+            opcode,
+            validator,
+            finished: false,
+        }
+    }
+
+    /// Validates and queues a frame of the message.
+    fn queue(&mut self, payload: &[u8], is_final: bool) -> io::Result<()> {
+        if self.stream.state != StreamState::Active {
+            return Err(io::Error::other(Error::AlreadyClosed));
+        }
+
+        // Another writer did not finish its message
+        if self.stream.writing_message && self.opcode != OpCode::Continuation {
+            return Err(io::Error::other(Error::FragmentationInProgress));
+        }
+
+        if let Some(validator) = &mut self.validator {
+            validator
+                .feed(payload, is_final)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+
+        self.stream.queue_frame(Frame {
+            opcode: self.opcode,
+            is_final,
+            payload: Payload::from(payload.to_vec()),
+        });
+        self.opcode = OpCode::Continuation;
+        self.finished = is_final;
+        self.stream.writing_message = !is_final;
+
+        Ok(())
+    }
+}
+
+impl<T> AsyncWrite for MessageWriter<'_, T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if this.finished {
+            return Poll::Ready(Err(io::Error::other(Error::AlreadyClosed)));
+        }
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        ready!(Pin::new(&mut *this.stream).poll_ready(cx)).map_err(io::Error::other)?;
+
+        let n = buf.len().min(this.stream.config.frame_size);
+        this.queue(&buf[..n], false)?;
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.get_mut().stream)
+            .poll_flush(cx)
+            .map_err(io::Error::other)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if !this.finished {
+            this.queue(&[], true)?;
+        }
+
+        Pin::new(&mut *this.stream)
+            .poll_flush(cx)
+            .map_err(io::Error::other)
+    }
+}
+
+impl<T> Drop for MessageWriter<'_, T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    fn drop(&mut self) {
+        // Frames of the unfinished message have been sent, continuing with another
+        // message would violate the protocol
+        if !self.finished
+            && self.opcode == OpCode::Continuation
+            && self.stream.state == StreamState::Active
+        {
+            self.stream.queue_frame(
+                Message::close(
+                    Some(CloseCode::INTERNAL_SERVER_ERROR),
+                    "message was not completed",
+                )
+                .into(),
+            );
+        }
+    }
+}
@@ -0,0 +1,294 @@
+//! A client stream that reconnects automatically after the connection was
+//! lost, see [`ReconnectingStream`].
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use tokio::net::TcpStream;
+
+use crate::{
+    ClientBuilder, Error, MaybeTlsStream, Message, WebSocketStream,
+    resolver::{self, Resolver},
+    upgrade,
+};
+
+/// The stream type created by [`ClientBuilder::connect`].
+type ClientStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Future of a connection attempt.
+type ConnectFuture =
+    Pin<Box<dyn Future<Output = Result<(ClientStream, upgrade::Response), Error>> + Send>>;
+
+/// Callback returning the messages to send after each connection, see
+/// [`ReconnectingStream::on_connect`].
+type ConnectCallback = dyn FnMut() -> Vec<Message> + Send;
+
+/// An item yielded by a [`ReconnectingStream`].
+#[derive(Debug)]
+pub enum Event {
+    /// A connection was established, either the first one or after a
+    /// reconnect. Messages received before may have been lost, so this is the
+    /// point to resynchronize the application state.
+    Connected,
+    /// A message was received.
+    Message(Message),
+}
+
+/// The connection state of a [`ReconnectingStream`].
+#[allow(clippy::large_enum_variant)] // The stream is used far more often than the others
+enum State {
+    /// A connection attempt is in progress, possibly waiting for its delay.
+    Connecting(ConnectFuture),
+    /// Connected to the server.
+    Connected(ClientStream),
+    /// The stream was closed or gave up reconnecting.
+    Done,
+}
+
+/// A client stream that reconnects with exponential backoff once the
+/// connection was lost.
+///
+/// The stream connects when it is first polled, yielding [`Event::Connected`]
+/// for that and every later connection, followed by the received messages.
+/// Errors of a connection are returned as usual, after which the connection
+/// ends and a new one is established. Failed connection attempts are retried
+/// after a delay that starts at the [minimum](ReconnectingStream::backoff) and
+/// doubles with every consecutive failure up to the maximum, with a random
+/// jitter of up to half the delay. Once the [maximum number of
+/// retries](ReconnectingStream::max_retries) is exceeded, the error of the last
+/// attempt is returned and the stream ends.
+///
+/// Messages can only be sent while connected, otherwise sending fails with
+/// [`Error::AlreadyClosed`]; they are not buffered across reconnects. Closing
+/// the stream performs the close handshake and stops reconnecting.
+pub struct ReconnectingStream<R: Resolver = resolver::Gai> {
+    /// Builder used to establish every connection.
+    builder: ClientBuilder<'static, R>,
+    /// The current connection state.
+    state: State,
+    /// Delay before the first retry.
+    min_delay: Duration,
+    /// Maximum delay between retries.
+    max_delay: Duration,
+    /// Maximum number of consecutive retries, unlimited if [`None`].
+    max_retries: Option<u32>,
+    /// Number of consecutive failed connection attempts.
+    failures: u32,
+    /// Callback returning the messages to send after each connection.
+    on_connect: Option<Box<ConnectCallback>>,
+}
+
+impl<R> ReconnectingStream<R>
+where
+    R: Resolver + Clone + Sync + 'static,
+{
+    /// Creates a stream that connects with a [`ClientBuilder`], which must
+    /// have a URI configured.
+    ///
+    /// By default, retries are delayed by 100 milliseconds up to 30 seconds
+    /// and are not limited in number.
+    #[must_use]
+    pub fn new(builder: ClientBuilder<'static, R>) -> Self {
+        let state = State::Connecting(Self::connect(builder.clone(), Duration::ZERO));
+
+        Self {
+            builder,
+            state,
+            min_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(30),
+            max_retries: None,
+            failures: 0,
+            on_connect: None,
+        }
+    }
+
+    /// Sets the delay before the first retry and the maximum delay between
+    /// retries.
+    #[must_use]
+    pub fn backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.min_delay = min;
+        self.max_delay = max.max(min);
+
+        self
+    }
+
+    /// Sets the maximum number of consecutive retries after a failed
+    /// connection attempt before giving up.
+    #[must_use]
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+
+        self
+    }
+
+    /// Sets a callback returning messages to send after each connection, e.g.
+    /// to authenticate or resubscribe. The messages are queued before
+    /// [`Event::Connected`] is yielded and sent the next time the stream is
+    /// flushed.
+    #[must_use]
+    pub fn on_connect<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut() -> Vec<Message> + Send + 'static,
+    {
+        self.on_connect = Some(Box::new(callback));
+
+        self
+    }
+
+    /// Returns a reference to the current connection, if connected.
+    pub fn get_ref(&self) -> Option<&ClientStream> {
+        match &self.state {
+            State::Connected(stream) => Some(stream),
+            State::Connecting(_) | State::Done => None,
+        }
+    }
+
+    /// Returns whether the stream is currently connected.
+    pub fn is_connected(&self) -> bool {
+        matches!(self.state, State::Connected(_))
+    }
+
+    /// Starts a connection attempt after a delay.
+    fn connect(builder: ClientBuilder<'static, R>, delay: Duration) -> ConnectFuture {
+        Box::pin(async move {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+
+            builder.connect().await
+        })
+    }
+
+    /// Returns the delay before the next retry after `failures` consecutive
+    /// failed attempts.
+    fn delay(&self) -> Duration {
+        let exponent = self.failures.saturating_sub(1).min(31);
+        let delay = self
+            .min_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+
+        let mut random = [0; 4];
+        crate::rand::get_mask(&mut random);
+        let jitter = f64::from(u32::from_ne_bytes(random)) / f64::from(u32::MAX);
+
+        delay.mul_f64(1.0 - jitter / 2.0)
+    }
+}
+
+impl<R> fmt::Debug for ReconnectingStream<R>
+where
+    R: Resolver,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match &self.state {
+            State::Connecting(_) => "Connecting",
+            State::Connected(_) => "Connected",
+            State::Done => "Done",
+        };
+
+        f.debug_struct("ReconnectingStream")
+            .field("state", &state)
+            .field("min_delay", &self.min_delay)
+            .field("max_delay", &self.max_delay)
+            .field("max_retries", &self.max_retries)
+            .field("failures", &self.failures)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R> Stream for ReconnectingStream<R>
+where
+    R: Resolver + Clone + Sync + Unpin + 'static,
+{
+    type Item = Result<Event, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            match &mut this.state {
+                State::Connecting(future) => match ready!(future.as_mut().poll(cx)) {
+                    Ok((mut stream, _)) => {
+                        this.failures = 0;
+                        let messages = this.on_connect.as_mut().map(|on_connect| on_connect());
+
+                        // Sending cannot fail on a new stream
+                        for message in messages.into_iter().flatten() {
+                            Pin::new(&mut stream).start_send(message)?;
+                        }
+
+                        this.state = State::Connected(stream);
+                        return Poll::Ready(Some(Ok(Event::Connected)));
+                    }
+                    Err(e) => {
+                        this.failures += 1;
+
+                        if this.max_retries.is_some_and(|max| this.failures > max) {
+                            this.state = State::Done;
+                            return Poll::Ready(Some(Err(e)));
+                        }
+
+                        let delay = this.delay();
+                        this.state = State::Connecting(Self::connect(this.builder.clone(), delay));
+                    }
+                },
+                State::Connected(stream) => match ready!(Pin::new(stream).poll_next(cx)) {
+                    Some(Ok(message)) => return Poll::Ready(Some(Ok(Event::Message(message)))),
+                    Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                    None => {
+                        this.state =
+                            State::Connecting(Self::connect(this.builder.clone(), Duration::ZERO));
+                    }
+                },
+                State::Done => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl<R> Sink<Message> for ReconnectingStream<R>
+where
+    R: Resolver + Unpin,
+{
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match &mut self.get_mut().state {
+            State::Connected(stream) => Pin::new(stream).poll_ready(cx),
+            State::Connecting(_) | State::Done => Poll::Ready(Err(Error::AlreadyClosed)),
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        match &mut self.get_mut().state {
+            State::Connected(stream) => Pin::new(stream).start_send(item),
+            State::Connecting(_) | State::Done => Err(Error::AlreadyClosed),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match &mut self.get_mut().state {
+            State::Connected(stream) => Pin::new(stream).poll_flush(cx),
+            State::Connecting(_) | State::Done => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        let result = match &mut this.state {
+            State::Connected(stream) => ready!(Pin::new(stream).poll_close(cx)),
+            State::Connecting(_) | State::Done => Ok(()),
+        };
+        this.state = State::Done;
+
+        Poll::Ready(result)
+    }
+}
@@ -6,26 +6,36 @@ use crate::Error;
 
 /// Trait for a DNS resolver to resolve hostnames and ports to IP addresses.
 pub trait Resolver: Send {
-    /// Resolve a hostname and port to an IP address, asynchronously.
+    /// Resolve a hostname and port to a list of IP addresses, asynchronously.
+    ///
+    /// The client attempts to connect to the addresses in the returned order.
     fn resolve(
         &self,
         host: &str,
         port: u16,
-    ) -> impl Future<Output = Result<SocketAddr, Error>> + Send;
+    ) -> impl Future<Output = Result<Vec<SocketAddr>, Error>> + Send;
 }
 
 /// A [`Resolver`] that uses the blocking `getaddrinfo` syscall in the tokio
 /// threadpool.
+///
+/// Failed lookups are returned as [`Error::Io`], while lookups that succeed
+/// without any addresses fail with [`Error::CannotResolveHost`].
+#[derive(Debug, Clone, Copy)]
 pub struct Gai;
 
 impl Resolver for Gai {
-    async fn resolve(&self, host: &str, port: u16) -> Result<SocketAddr, Error> {
+    async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, Error> {
         let host = host.to_owned();
 
-        tokio::net::lookup_host((host, port))
-            .await
-            .map_err(|_| Error::CannotResolveHost)?
-            .next()
-            .ok_or(Error::CannotResolveHost)
+        // The lookup error is kept so that its cause is available via
+        // `Error::source`
+        let addrs: Vec<_> = tokio::net::lookup_host((host, port)).await?.collect();
+
+        if addrs.is_empty() {
+            return Err(Error::CannotResolveHost);
+        }
+
+        Ok(addrs)
     }
 }
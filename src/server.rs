@@ -1,26 +1,110 @@
 //! Implementation of a WebSocket server.
 //!
-//! This can be used in two ways:
+//! This can be used in three ways:
 //!   - By letting the library perform a HTTP/1.1 Upgrade handshake on an
 //!     established stream, via [`Builder::accept`]
 //!   - By performing the handshake yourself and then using [`Builder::serve`]
 //!     to let it take over a WebSocket stream
-use std::{future::poll_fn, io, pin::Pin};
+//!   - By letting a HTTP server framework parse the request and respond with
+//!     [`Builder::upgrade_response`], then handing the upgraded connection to
+//!     [`Builder::accept_from_request`]
+use std::{io, sync::Arc};
 
-use futures_core::Stream;
-use http::{HeaderMap, HeaderName, HeaderValue, header};
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header};
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 use tokio_util::codec::FramedRead;
 
 use crate::{
     Error, WebSocketStream,
-    proto::{Config, Limits, Role},
-    upgrade::client_request,
+    proto::{Config, Limits, Observer, Role},
+    upgrade::{self, client_request},
 };
 
 /// HTTP/1.1 400 Bad Request response payload.
 const BAD_REQUEST: &[u8] = b"HTTP/1.1 400 Bad Request\r\n\r\n";
 
+/// HTTP/1.1 426 Upgrade Required response payload, advertising the supported
+/// WebSocket protocol version.
+const UPGRADE_REQUIRED: &[u8] =
+    b"HTTP/1.1 426 Upgrade Required\r\nSec-WebSocket-Version: 13\r\n\r\n";
+
+/// HTTP/1.1 500 Internal Server Error response payload.
+const INTERNAL_SERVER_ERROR: &[u8] = b"HTTP/1.1 500 Internal Server Error\r\n\r\n";
+
+/// Callback to inspect the client's handshake request, see
+/// [`Builder::on_request`].
+type RequestCallback = dyn Fn(&http::Request<()>) -> Result<HeaderMap, Rejection> + Send + Sync;
+
+/// A response rejecting the client's handshake request, returned by the
+/// callback set via [`Builder::on_request`].
+#[derive(Debug)]
+pub struct Rejection(Box<http::Response<Vec<u8>>>);
+
+impl Rejection {
+    /// Creates a rejection with a status code and an empty body.
+    #[must_use]
+    pub fn new(status: StatusCode) -> Self {
+        let mut response = http::Response::new(Vec::new());
+        *response.status_mut() = status;
+
+        Self(Box::new(response))
+    }
+
+    /// Adds a header to the response.
+    #[must_use]
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.0.headers_mut().append(name, value);
+
+        self
+    }
+
+    /// Sets the body of the response.
+    #[must_use]
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        *self.0.body_mut() = body.into();
+
+        self
+    }
+
+    /// Returns the status code of the response.
+    #[must_use]
+    pub fn status(&self) -> StatusCode {
+        self.0.status()
+    }
+
+    /// Serializes the response.
+    fn to_bytes(&self) -> Vec<u8> {
+        let response = &self.0;
+        let status = response.status();
+        let mut resp = Vec::with_capacity(64 + response.body().len());
+
+        resp.extend_from_slice(b"HTTP/1.1 ");
+        resp.extend_from_slice(status.as_str().as_bytes());
+        resp.push(b' ');
+        resp.extend_from_slice(status.canonical_reason().unwrap_or_default().as_bytes());
+        resp.extend_from_slice(b"\r\n");
+
+        client_request::write_headers(&mut resp, response.headers());
+
+        if !response.headers().contains_key(header::CONTENT_LENGTH) {
+            resp.extend_from_slice(b"content-length: ");
+            resp.extend_from_slice(response.body().len().to_string().as_bytes());
+            resp.extend_from_slice(b"\r\n");
+        }
+
+        resp.extend_from_slice(b"\r\n");
+        resp.extend_from_slice(response.body());
+
+        resp
+    }
+}
+
+impl From<http::Response<Vec<u8>>> for Rejection {
+    fn from(response: http::Response<Vec<u8>>) -> Self {
+        Self(Box::new(response))
+    }
+}
+
 /// List of headers added by the server which will cause an error
 /// if added by the user:
 ///
@@ -28,10 +112,12 @@ const BAD_REQUEST: &[u8] = b"HTTP/1.1 400 Bad Request\r\n\r\n";
 /// - `upgrade`
 /// - `connection`
 /// - `sec-websocket-accept`
+/// - `sec-websocket-protocol` (use [`Builder::add_subprotocol`] instead)
 pub const DISALLOWED_HEADERS: &[HeaderName] = &[
     header::UPGRADE,
     header::CONNECTION,
     header::SEC_WEBSOCKET_ACCEPT,
+    header::SEC_WEBSOCKET_PROTOCOL,
 ];
 
 /// Builder for WebSocket server connections.
@@ -42,6 +128,18 @@ pub struct Builder {
     limits: Limits,
     /// Headers to be sent with the switching protocols response.
     headers: HeaderMap,
+    /// Subprotocols supported by the server, in order of preference.
+    subprotocols: Vec<String>,
+    /// Whether to reject clients that do not offer any of the subprotocols.
+    require_subprotocol: bool,
+    /// Whether to echo the offered subprotocols if none of them is supported.
+    echo_all_subprotocols: bool,
+    /// Callback to inspect the handshake request before accepting it.
+    on_request: Option<Box<RequestCallback>>,
+    /// Maximum length of the head of the handshake request.
+    max_handshake_len: usize,
+    /// Observer notified of the handshake and the frames of connections.
+    observer: Option<Arc<dyn Observer>>,
 }
 
 impl Default for Builder {
@@ -59,6 +157,12 @@ impl Builder {
             config: Config::default(),
             limits: Limits::default(),
             headers: HeaderMap::new(),
+            subprotocols: Vec::new(),
+            require_subprotocol: false,
+            echo_all_subprotocols: false,
+            on_request: None,
+            max_handshake_len: upgrade::DEFAULT_MAX_HANDSHAKE_LEN,
+            observer: None,
         }
     }
 
@@ -78,7 +182,18 @@ impl Builder {
         self
     }
 
-    /// Adds an extra HTTP header to the switching protocols response.
+    /// Sets an observer that is notified of the outcome of handshakes and of
+    /// the frames sent and received by the streams created by this builder,
+    /// e.g. to collect metrics.
+    #[must_use]
+    pub fn observer(mut self, observer: Arc<dyn Observer>) -> Self {
+        self.observer = Some(observer);
+
+        self
+    }
+
+    /// Adds an extra HTTP header to the switching protocols response,
+    /// replacing any previously added values of the header.
     ///
     /// # Errors
     ///
@@ -93,6 +208,115 @@ impl Builder {
         Ok(self)
     }
 
+    /// Adds an extra HTTP header to the switching protocols response, keeping
+    /// any previously added values of the header.
+    ///
+    /// Multiple `Set-Cookie` values are sent on their own header lines, values
+    /// of other headers are joined with commas.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::DisallowedHeader`] if the header is in
+    /// the [`DISALLOWED_HEADERS`] list.
+    pub fn append_header(mut self, name: HeaderName, value: HeaderValue) -> Result<Self, Error> {
+        if DISALLOWED_HEADERS.contains(&name) {
+            return Err(Error::DisallowedHeader);
+        }
+        self.headers.append(name, value);
+
+        Ok(self)
+    }
+
+    /// Adds a subprotocol supported by the server.
+    ///
+    /// Subprotocols are preferred in the order they are added. If the client
+    /// offers any of them via the `Sec-WebSocket-Protocol` header, the most
+    /// preferred one is selected and sent back in the switching protocols
+    /// response. It is available via [`WebSocketStream::subprotocol`] once
+    /// accepted.
+    ///
+    /// # Panics
+    ///
+    /// If `subprotocol` is not a valid HTTP token.
+    #[must_use]
+    pub fn add_subprotocol(mut self, subprotocol: impl Into<String>) -> Self {
+        let subprotocol = subprotocol.into();
+        assert!(
+            upgrade::is_token(&subprotocol),
+            "subprotocol must be a valid HTTP token"
+        );
+        self.subprotocols.push(subprotocol);
+
+        self
+    }
+
+    /// Sets whether the handshake fails if the client does not offer any of
+    /// the subprotocols added via [`Builder::add_subprotocol`].
+    ///
+    /// If enabled, such handshakes are answered with a 400 Bad Request
+    /// response and [`Builder::accept`] fails with
+    /// [`upgrade::Error::NoMatchingSubprotocol`]. Defaults to `false`.
+    #[must_use]
+    pub fn require_subprotocol(mut self, require_subprotocol: bool) -> Self {
+        self.require_subprotocol = require_subprotocol;
+
+        self
+    }
+
+    /// Sets whether the switching protocols response echoes all subprotocols
+    /// offered by the client if none of the ones added via
+    /// [`Builder::add_subprotocol`] is offered. [`WebSocketStream::subprotocol`]
+    /// returns [`None`] in that case.
+    ///
+    /// This violates RFC 6455, which allows the server to select at most one
+    /// of the offered subprotocols, and conforming clients fail the handshake
+    /// when receiving such a response. It only exists to interoperate with
+    /// legacy clients that expect their list echoed back. Defaults to `false`.
+    #[must_use]
+    pub fn echo_all_subprotocols(mut self, echo_all_subprotocols: bool) -> Self {
+        self.echo_all_subprotocols = echo_all_subprotocols;
+
+        self
+    }
+
+    /// Sets the maximum length of the head of the client's handshake request.
+    /// The default is 64 KiB.
+    ///
+    /// Longer requests are answered with a 400 Bad Request response and
+    /// [`Builder::accept`] fails with [`upgrade::Error::HandshakeTooLarge`]
+    /// instead of buffering them indefinitely. The same applies to requests
+    /// with more than 64 headers regardless of their length.
+    #[must_use]
+    pub fn max_handshake_len(mut self, max_len: usize) -> Self {
+        self.max_handshake_len = max_len;
+
+        self
+    }
+
+    /// Sets a callback that inspects the client's handshake request before it
+    /// is accepted, e.g. to authenticate the client, check the `Origin` header
+    /// or route based on the request path.
+    ///
+    /// The callback is only called for valid WebSocket handshake requests.
+    /// Returning `Ok` accepts the request and adds the returned headers to the
+    /// switching protocols response, in addition to the ones added via
+    /// [`Builder::add_header`]. Returning `Err` rejects the request with the
+    /// given [`Rejection`] response instead, in which case [`Builder::accept`]
+    /// fails with [`upgrade::Error::Rejected`].
+    ///
+    /// If the returned headers contain any of the [`DISALLOWED_HEADERS`], the
+    /// request is answered with a 500 Internal Server Error response and
+    /// [`Builder::accept`] fails with [`Error::DisallowedHeader`].
+    #[must_use]
+    pub fn on_request<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&http::Request<()>) -> Result<HeaderMap, Rejection> + Send + Sync + 'static,
+    {
+        self.on_request = Some(Box::new(callback));
+
+        self
+    }
+
     /// Perform a HTTP upgrade handshake on an already established stream and
     /// uses it to send and receive WebSocket messages.
     ///
@@ -103,24 +327,84 @@ impl Builder {
         &self,
         stream: S,
     ) -> Result<(http::Request<()>, WebSocketStream<S>), Error> {
-        let mut framed = FramedRead::new(
-            stream,
-            client_request::Codec {
-                response_headers: &self.headers,
-            },
-        );
-        let reply = poll_fn(|cx| Pin::new(&mut framed).poll_next(cx)).await;
+        self.accept_with_prefix(stream, &[]).await
+    }
+
+    /// Performs the HTTP upgrade handshake like [`Builder::accept`], with
+    /// bytes of the client's request that were already read from the stream,
+    /// e.g. while detecting the protocol spoken on it.
+    ///
+    /// The prefix is parsed before reading from the stream. Any bytes after
+    /// the request are kept as the start of the WebSocket data.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an [`Error`] if the handshake fails.
+    pub async fn accept_with_prefix<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        stream: S,
+        prefix: &[u8],
+    ) -> Result<(http::Request<()>, WebSocketStream<S>), Error> {
+        let result = self.upgrade(stream, prefix).await;
+
+        if let Some(observer) = &self.observer {
+            observer.handshake(result.as_ref().map(drop));
+        }
+
+        result
+    }
+
+    /// Reads the HTTP upgrade request from a stream and responds to it, see
+    /// [`Builder::accept`].
+    async fn upgrade<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        stream: S,
+        prefix: &[u8],
+    ) -> Result<(http::Request<()>, WebSocketStream<S>), Error> {
+        let mut framed = FramedRead::new(stream, self.codec());
+        let reply = upgrade::read_head(&mut framed, prefix).await;
 
         match reply {
-            Some(Ok((request, response))) => {
+            Some(Ok((request, mut response, subprotocol))) => {
+                if let Some(on_request) = &self.on_request {
+                    match on_request(&request) {
+                        Ok(headers) => {
+                            if DISALLOWED_HEADERS
+                                .iter()
+                                .any(|name| headers.contains_key(name))
+                            {
+                                framed.get_mut().write_all(INTERNAL_SERVER_ERROR).await?;
+
+                                return Err(Error::DisallowedHeader);
+                            }
+
+                            client_request::write_headers(&mut response, &headers);
+                        }
+                        Err(rejected) => {
+                            framed.get_mut().write_all(&rejected.to_bytes()).await?;
+
+                            return Err(upgrade::Error::Rejected(rejected.status().as_u16()).into());
+                        }
+                    }
+                }
+
+                response.extend_from_slice(b"\r\n");
                 framed.get_mut().write_all(&response).await?;
-                Ok((
-                    request,
-                    WebSocketStream::from_framed(framed, Role::Server, self.config, self.limits),
-                ))
+
+                let mut stream =
+                    WebSocketStream::from_framed(framed, Role::Server, self.config, self.limits);
+                stream.set_subprotocol(subprotocol);
+                stream.set_observer(self.observer.clone());
+
+                Ok((request, stream))
             }
             Some(Err(e)) => {
-                framed.get_mut().write_all(BAD_REQUEST).await?;
+                // RFC 6455 requires telling the client which versions are supported
+                let response = match e {
+                    Error::Upgrade(upgrade::Error::UnsupportedWebSocketVersion) => UPGRADE_REQUIRED,
+                    _ => BAD_REQUEST,
+                };
+                framed.get_mut().write_all(response).await?;
 
                 Err(e)
             }
@@ -128,11 +412,105 @@ impl Builder {
         }
     }
 
+    /// Builds the switching protocols response to a handshake request that was
+    /// already parsed, e.g. by a HTTP server framework, to be sent by the
+    /// caller before handing the upgraded connection to
+    /// [`Builder::accept_from_request`].
+    ///
+    /// The response contains the selected subprotocol and the headers added
+    /// via [`Builder::add_header`]. The callback set via
+    /// [`Builder::on_request`] is not called, since the caller is in control
+    /// of the request anyway.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an [`Error`] if the request is not a valid
+    /// WebSocket handshake request.
+    #[allow(clippy::missing_panics_doc)] // The header values are always valid
+    pub fn upgrade_response(
+        &self,
+        request: &http::Request<()>,
+    ) -> Result<http::Response<()>, Error> {
+        let codec = self.codec();
+        let (ws_accept, subprotocol) = codec.validate(request)?;
+
+        let mut response = http::Response::new(());
+        *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+
+        let headers = response.headers_mut();
+        headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(header::CONNECTION, HeaderValue::from_static("Upgrade"));
+        headers.insert(
+            header::SEC_WEBSOCKET_ACCEPT,
+            HeaderValue::try_from(ws_accept).expect("base64 is a valid header value"),
+        );
+        if let Some(subprotocol) = subprotocol {
+            headers.insert(
+                header::SEC_WEBSOCKET_PROTOCOL,
+                HeaderValue::try_from(subprotocol).expect("subprotocols are HTTP tokens"),
+            );
+        } else if let Some(offered) = codec.echoed_subprotocols(request.headers()) {
+            headers.insert(header::SEC_WEBSOCKET_PROTOCOL, offered);
+        }
+        headers.extend(self.headers.clone());
+
+        Ok(response)
+    }
+
+    /// Takes over a connection that was upgraded in response to a handshake
+    /// request that was already parsed, e.g. the one returned by hyper's
+    /// `on_upgrade` after responding to the request.
+    ///
+    /// The request is validated and the subprotocol is selected like in
+    /// [`Builder::accept`], but no response is written: the caller must have
+    /// sent the switching protocols response already, typically the one built
+    /// by [`Builder::upgrade_response`]. The callback set via
+    /// [`Builder::on_request`] is not called.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an [`Error`] if the request is not a valid
+    /// WebSocket handshake request.
+    pub fn accept_from_request<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        request: &http::Request<()>,
+        upgraded: S,
+    ) -> Result<WebSocketStream<S>, Error> {
+        let result = self.codec().validate(request).map(|(_, subprotocol)| {
+            let mut stream = self.serve(upgraded);
+            stream.set_subprotocol(subprotocol);
+
+            stream
+        });
+
+        if let Some(observer) = &self.observer {
+            observer.handshake(result.as_ref().map(drop));
+        }
+
+        result
+    }
+
+    /// Returns the codec validating handshake requests with the settings of
+    /// this builder.
+    fn codec(&self) -> client_request::Codec<'_> {
+        client_request::Codec {
+            response_headers: &self.headers,
+            subprotocols: &self.subprotocols,
+            require_subprotocol: self.require_subprotocol,
+            echo_all_subprotocols: self.echo_all_subprotocols,
+            max_len: self.max_handshake_len,
+        }
+    }
+
     /// Takes over an already established stream and uses it to send and receive
     /// WebSocket messages.
     ///
     /// This does not perform a HTTP upgrade handshake.
     pub fn serve<S: AsyncRead + AsyncWrite + Unpin>(&self, stream: S) -> WebSocketStream<S> {
-        WebSocketStream::from_raw_stream(stream, Role::Server, self.config, self.limits)
+        let mut stream =
+            WebSocketStream::from_raw_stream(stream, Role::Server, self.config, self.limits);
+        stream.set_observer(self.observer.clone());
+
+        stream
     }
 }
@@ -4,10 +4,13 @@
 //! [`native-tls`]: tokio_native_tls::native_tls
 //! [`rustls`]: tokio_rustls::rustls
 
+#[cfg(feature = "client")]
+use std::net::SocketAddr;
 #[cfg(any(
     feature = "rustls-webpki-roots",
     feature = "rustls-native-roots",
-    feature = "rustls-platform-verifier"
+    feature = "rustls-platform-verifier",
+    feature = "rustls-bring-your-own-connector"
 ))]
 use std::sync::Arc;
 use std::{
@@ -27,16 +30,131 @@ use rustls_pki_types::ServerName;
 #[cfg(feature = "rustls-platform-verifier")]
 use rustls_platform_verifier::BuilderVerifierExt;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+#[cfg(feature = "client")]
+use tokio::net::TcpStream;
 #[cfg(any(
     feature = "rustls-native-roots",
     feature = "rustls-webpki-roots",
-    feature = "rustls-platform-verifier"
+    feature = "rustls-platform-verifier",
+    feature = "rustls-bring-your-own-connector"
 ))]
 use tokio_rustls::rustls::ClientConfig;
+#[cfg(any(
+    feature = "rustls-native-roots",
+    feature = "rustls-webpki-roots",
+    feature = "rustls-platform-verifier",
+    feature = "rustls-bring-your-own-connector"
+))]
+use tokio_rustls::rustls::{
+    CipherSuite, DigitallySignedStruct, ProtocolVersion, SignatureScheme,
+    client::{
+        ClientSessionStore, ResolvesClientCert, Resumption,
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    },
+    crypto::{CryptoProvider, verify_tls12_signature, verify_tls13_signature},
+    sign::CertifiedKey,
+};
 
 use crate::Error;
 
+/// A [`ResolvesClientCert`] that always presents the same certificate chain,
+/// see [`Connector::client_auth`].
+#[cfg(any(
+    feature = "rustls-native-roots",
+    feature = "rustls-webpki-roots",
+    feature = "rustls-platform-verifier",
+    feature = "rustls-bring-your-own-connector"
+))]
+#[derive(Debug)]
+struct ClientCertificate(Arc<CertifiedKey>);
+
+#[cfg(any(
+    feature = "rustls-native-roots",
+    feature = "rustls-webpki-roots",
+    feature = "rustls-platform-verifier",
+    feature = "rustls-bring-your-own-connector"
+))]
+impl ResolvesClientCert for ClientCertificate {
+    fn resolve(
+        &self,
+        _root_hint_subjects: &[&[u8]],
+        _sigschemes: &[SignatureScheme],
+    ) -> Option<Arc<CertifiedKey>> {
+        Some(self.0.clone())
+    }
+
+    fn has_certs(&self) -> bool {
+        true
+    }
+}
+
+/// A [`ServerCertVerifier`] that accepts any certificate, but still verifies
+/// handshake signatures, see [`Connector::dangerous_accept_invalid_certs`].
+#[cfg(any(
+    feature = "rustls-native-roots",
+    feature = "rustls-webpki-roots",
+    feature = "rustls-platform-verifier",
+    feature = "rustls-bring-your-own-connector"
+))]
+#[derive(Debug)]
+struct NoCertificateVerification(Arc<CryptoProvider>);
+
+#[cfg(any(
+    feature = "rustls-native-roots",
+    feature = "rustls-webpki-roots",
+    feature = "rustls-platform-verifier",
+    feature = "rustls-bring-your-own-connector"
+))]
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls_pki_types::CertificateDer<'_>,
+        _intermediates: &[rustls_pki_types::CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls_pki_types::UnixTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls_pki_types::CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls_pki_types::CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
 /// A reusable TLS connector for wrapping streams.
+///
+/// Cloning a connector is cheap, the clones share the same TLS
+/// configuration.
+#[derive(Clone)]
 pub enum Connector {
     /// Plain (non-TLS) connector.
     Plain,
@@ -47,7 +165,12 @@ pub enum Connector {
     NativeTls(tokio_native_tls::TlsConnector),
     /// [`rustls`] TLS connector.
     ///
+    /// An existing [`ClientConfig`] can be reused via the [`From`]
+    /// implementation, sharing e.g. its session cache with other users of the
+    /// config.
+    ///
     /// [`rustls`]: tokio_rustls::rustls
+    /// [`ClientConfig`]: tokio_rustls::rustls::ClientConfig
     #[cfg(any(
         feature = "rustls-native-roots",
         feature = "rustls-webpki-roots",
@@ -57,6 +180,18 @@ pub enum Connector {
     Rustls(tokio_rustls::TlsConnector),
 }
 
+#[cfg(any(
+    feature = "rustls-native-roots",
+    feature = "rustls-webpki-roots",
+    feature = "rustls-platform-verifier",
+    feature = "rustls-bring-your-own-connector"
+))]
+impl From<Arc<ClientConfig>> for Connector {
+    fn from(config: Arc<ClientConfig>) -> Self {
+        Self::Rustls(tokio_rustls::TlsConnector::from(config))
+    }
+}
+
 impl Debug for Connector {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
@@ -74,6 +209,43 @@ impl Debug for Connector {
     }
 }
 
+#[cfg(any(
+    feature = "rustls-native-roots",
+    feature = "rustls-webpki-roots",
+    feature = "rustls-platform-verifier",
+    feature = "rustls-bring-your-own-connector"
+))]
+/// Parameters negotiated during a TLS handshake, returned by
+/// [`MaybeTlsStream::tls_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TlsInfo {
+    /// The negotiated TLS protocol version.
+    protocol_version: ProtocolVersion,
+    /// The negotiated cipher suite.
+    cipher_suite: CipherSuite,
+}
+
+#[cfg(any(
+    feature = "rustls-native-roots",
+    feature = "rustls-webpki-roots",
+    feature = "rustls-platform-verifier",
+    feature = "rustls-bring-your-own-connector"
+))]
+impl TlsInfo {
+    /// Returns the negotiated TLS protocol version, e.g.
+    /// [`ProtocolVersion::TLSv1_3`].
+    #[must_use]
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    /// Returns the negotiated cipher suite.
+    #[must_use]
+    pub fn cipher_suite(&self) -> CipherSuite {
+        self.cipher_suite
+    }
+}
+
 /// A stream that might be protected with TLS.
 #[allow(clippy::large_enum_variant)] // Only one or two of these will be used
 #[derive(Debug)]
@@ -97,6 +269,112 @@ pub enum MaybeTlsStream<S> {
     Rustls(tokio_rustls::client::TlsStream<S>),
 }
 
+impl<S> MaybeTlsStream<S> {
+    /// Returns a reference to the underlying socket stream.
+    ///
+    /// Care should be taken not to read from or write to the stream directly
+    /// to avoid corrupting the TLS session.
+    pub fn get_ref(&self) -> &S {
+        match self {
+            Self::Plain(s) => s,
+            #[cfg(feature = "native-tls")]
+            Self::NativeTls(s) => s.get_ref().get_ref().get_ref(),
+            #[cfg(any(
+                feature = "rustls-native-roots",
+                feature = "rustls-webpki-roots",
+                feature = "rustls-platform-verifier",
+                feature = "rustls-bring-your-own-connector"
+            ))]
+            Self::Rustls(s) => s.get_ref().0,
+        }
+    }
+
+    /// Returns whether the stream is encrypted with TLS.
+    ///
+    /// For a connected [`WebSocketStream`], this is available via
+    /// [`WebSocketStream::get_ref`].
+    ///
+    /// [`WebSocketStream`]: crate::WebSocketStream
+    /// [`WebSocketStream::get_ref`]: crate::WebSocketStream::get_ref
+    pub fn is_tls(&self) -> bool {
+        !matches!(self, Self::Plain(_))
+    }
+
+    /// Returns the protocol negotiated via ALPN during the TLS handshake, if
+    /// any.
+    ///
+    /// This always returns [`None`] for unencrypted streams and for streams
+    /// using [`native-tls`], which does not expose the negotiated protocol.
+    ///
+    /// [`native-tls`]: tokio_native_tls::native_tls
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        match self {
+            Self::Plain(_) => None,
+            #[cfg(feature = "native-tls")]
+            Self::NativeTls(_) => None,
+            #[cfg(any(
+                feature = "rustls-native-roots",
+                feature = "rustls-webpki-roots",
+                feature = "rustls-platform-verifier",
+                feature = "rustls-bring-your-own-connector"
+            ))]
+            Self::Rustls(s) => s.get_ref().1.alpn_protocol(),
+        }
+    }
+
+    /// Returns the protocol version and cipher suite negotiated during the
+    /// TLS handshake.
+    ///
+    /// This always returns [`None`] for unencrypted streams and for streams
+    /// using [`native-tls`], which does not expose the negotiated parameters.
+    ///
+    /// [`native-tls`]: tokio_native_tls::native_tls
+    #[cfg(any(
+        feature = "rustls-native-roots",
+        feature = "rustls-webpki-roots",
+        feature = "rustls-platform-verifier",
+        feature = "rustls-bring-your-own-connector"
+    ))]
+    pub fn tls_info(&self) -> Option<TlsInfo> {
+        match self {
+            Self::Plain(_) => None,
+            #[cfg(feature = "native-tls")]
+            Self::NativeTls(_) => None,
+            Self::Rustls(s) => {
+                let connection = s.get_ref().1;
+
+                Some(TlsInfo {
+                    protocol_version: connection.protocol_version()?,
+                    cipher_suite: connection.negotiated_cipher_suite()?.suite(),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl MaybeTlsStream<TcpStream> {
+    /// Returns the remote address that the underlying socket is connected to.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an [`io::Error`] if the address cannot be
+    /// retrieved from the socket.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.get_ref().peer_addr()
+    }
+
+    /// Returns the local address that the underlying socket is bound to.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an [`io::Error`] if the address cannot be
+    /// retrieved from the socket.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.get_ref().local_addr()
+    }
+}
+
 impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for MaybeTlsStream<S> {
     fn poll_read(
         self: Pin<&mut Self>,
@@ -207,6 +485,17 @@ impl Connector {
     /// Creates a new `Connector` with the underlying TLS library specified in
     /// the feature flags.
     ///
+    /// If multiple TLS features are enabled, rustls is preferred over
+    /// `native-tls`. For rustls, `rustls-platform-verifier` takes precedence
+    /// over the root certificate features. With `rustls-native-roots`, the
+    /// certificates installed in the OS trust store are loaded, so that servers
+    /// signed by internal CAs are trusted. If `rustls-webpki-roots` is enabled
+    /// as well, the union of both root stores is trusted.
+    ///
+    /// With rustls, `http/1.1` is offered via ALPN, which some servers require
+    /// to accept the handshake. To offer other protocols, create the connector
+    /// from a custom [`ClientConfig`] with its `alpn_protocols` set instead.
+    ///
     /// # Errors
     ///
     /// This method returns an [`Error`] when creating the underlying TLS
@@ -216,6 +505,8 @@ impl Connector {
     ///
     /// Panics if no rustls crypto provider is installed and rustls is unable
     /// to determine a default crypto provider from its feature flags.
+    ///
+    /// [`ClientConfig`]: tokio_rustls::rustls::ClientConfig
     pub fn new() -> Result<Self, Error> {
         #[cfg(not(any(
             feature = "native-tls",
@@ -279,10 +570,114 @@ impl Connector {
                 config_builder.with_root_certificates(roots)
             };
 
-            Ok(Self::Rustls(tokio_rustls::TlsConnector::from(Arc::new(
-                config_builder.with_no_client_auth(),
-            ))))
+            let mut config = config_builder.with_no_client_auth();
+            config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+            Ok(Arc::new(config).into())
+        }
+    }
+
+    /// Disables validation of the server's certificate, accepting any
+    /// certificate, including self-signed and expired ones and those issued
+    /// for other server names.
+    ///
+    /// **This is insecure** and makes connections vulnerable to
+    /// man-in-the-middle attacks. It is only meant for testing against local
+    /// servers with self-signed certificates.
+    ///
+    /// Only [`Connector::Rustls`] connectors are affected, other connectors
+    /// are returned unchanged. The signatures of the TLS handshake are still
+    /// verified with the crypto provider of the connector's configuration.
+    #[cfg(any(
+        feature = "rustls-native-roots",
+        feature = "rustls-webpki-roots",
+        feature = "rustls-platform-verifier",
+        feature = "rustls-bring-your-own-connector"
+    ))]
+    #[must_use]
+    pub fn dangerous_accept_invalid_certs(self) -> Self {
+        let Self::Rustls(connector) = self else {
+            return self;
+        };
+
+        let mut config = ClientConfig::clone(connector.config());
+        let provider = config.crypto_provider().clone();
+        config
+            .dangerous()
+            .set_certificate_verifier(Arc::new(NoCertificateVerification(provider)));
+
+        Arc::new(config).into()
+    }
+
+    /// Sets the store for TLS sessions that allows resuming them when
+    /// reconnecting to a server, which avoids a full TLS handshake.
+    ///
+    /// By default, rustls connectors store up to 256 sessions in memory. The
+    /// store is shared by all clones of a connector, so reusing one connector
+    /// for many connections, e.g. via [`ClientBuilder::connector`] or by
+    /// cloning a [`ClientBuilder`], is enough to resume sessions. A custom
+    /// store allows sharing sessions with other users of rustls. Only
+    /// [`Connector::Rustls`] connectors are affected, other connectors are
+    /// returned unchanged.
+    ///
+    /// [`ClientBuilder`]: crate::ClientBuilder
+    /// [`ClientBuilder::connector`]: crate::ClientBuilder::connector
+    #[cfg(any(
+        feature = "rustls-native-roots",
+        feature = "rustls-webpki-roots",
+        feature = "rustls-platform-verifier",
+        feature = "rustls-bring-your-own-connector"
+    ))]
+    #[must_use]
+    pub fn session_store(self, store: Arc<dyn ClientSessionStore>) -> Self {
+        let Self::Rustls(connector) = self else {
+            return self;
+        };
+
+        let mut config = ClientConfig::clone(connector.config());
+        config.resumption = Resumption::store(store);
+
+        Arc::new(config).into()
+    }
+
+    /// Presents a client certificate chain to servers that request client
+    /// authentication (mutual TLS).
+    ///
+    /// The first certificate of `certs` must be the end-entity certificate
+    /// matching `key`.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Error::NotRustlsConnector`] if the connector is
+    /// not a [`Connector::Rustls`], since client certificates cannot be
+    /// presented with other connectors. It returns an [`Error::Rustls`] if
+    /// `certs` is empty, the key type is not supported by the crypto provider
+    /// of the connector's configuration or the key does not match the
+    /// end-entity certificate.
+    #[cfg(any(
+        feature = "rustls-native-roots",
+        feature = "rustls-webpki-roots",
+        feature = "rustls-platform-verifier",
+        feature = "rustls-bring-your-own-connector"
+    ))]
+    pub fn client_auth(
+        self,
+        certs: Vec<rustls_pki_types::CertificateDer<'static>>,
+        key: rustls_pki_types::PrivateKeyDer<'static>,
+    ) -> Result<Self, Error> {
+        let Self::Rustls(connector) = self else {
+            return Err(Error::NotRustlsConnector);
+        };
+
+        if certs.is_empty() {
+            return Err(tokio_rustls::rustls::Error::NoCertificatesPresented.into());
         }
+
+        let mut config = ClientConfig::clone(connector.config());
+        let certified_key = CertifiedKey::from_der(certs, key, config.crypto_provider())?;
+        config.client_auth_cert_resolver = Arc::new(ClientCertificate(Arc::new(certified_key)));
+
+        Ok(Arc::new(config).into())
     }
 
     /// Wraps a given stream with a layer of TLS.
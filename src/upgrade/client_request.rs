@@ -3,32 +3,31 @@ use std::str::FromStr;
 
 use base64::{Engine, engine::general_purpose::STANDARD};
 use bytes::{Buf, BytesMut};
-use http::{HeaderMap, header::SET_COOKIE};
+use http::{
+    HeaderMap,
+    header::{SEC_WEBSOCKET_PROTOCOL, SET_COOKIE},
+};
 use httparse::Request;
 use tokio_util::codec::Decoder;
 
-use crate::{sha::digest, upgrade::Error};
+use crate::{
+    sha::digest,
+    upgrade::{Error, MAX_REQUEST_HEADERS},
+};
 
 /// A static HTTP/1.1 101 Switching Protocols response up until the
 /// `Sec-WebSocket-Accept` header value.
 const SWITCHING_PROTOCOLS_BODY: &[u8] = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: ";
 
-/// Returns whether an ASCII byte slice is contained in another one, ignoring
-/// captalization.
-fn contains_ignore_ascii_case(mut haystack: &[u8], needle: &[u8]) -> bool {
-    if needle.is_empty() {
-        return true;
-    }
-
-    while haystack.len() >= needle.len() {
-        if haystack[..needle.len()].eq_ignore_ascii_case(needle) {
-            return true;
-        }
-
-        haystack = &haystack[1..];
-    }
-
-    false
+/// Returns whether any value of a header is a comma-separated list containing
+/// a token, ignoring capitalization.
+fn contains_token(headers: &HeaderMap, name: &str, token: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| value.trim().eq_ignore_ascii_case(token))
 }
 
 /// A client's opening handshake.
@@ -43,12 +42,14 @@ impl ClientRequest {
     /// # Errors
     ///
     /// This method fails when a header required for the WebSocket protocol is
-    /// missing in the handshake.
-    pub fn parse<'a, F>(header: F) -> Result<Self, Error>
-    where
-        F: Fn(&'static str) -> Option<&'a str> + 'a,
-    {
-        let find_header = |name| header(name).ok_or(super::Error::MissingHeader(name));
+    /// missing in the handshake or has an invalid value.
+    pub fn parse(headers: &HeaderMap) -> Result<Self, Error> {
+        let find_header = |name| {
+            headers
+                .get(name)
+                .and_then(|h| h.to_str().ok())
+                .ok_or(Error::MissingHeader(name))
+        };
 
         let check_header = |name, expected, err| {
             let actual = find_header(name)?;
@@ -59,16 +60,18 @@ impl ClientRequest {
             }
         };
 
-        let check_header_contains = |name, expected: &str, err| {
-            let actual = find_header(name)?;
-            if contains_ignore_ascii_case(actual.as_bytes(), expected.as_bytes()) {
+        // Both headers are token lists and may be repeated, e.g.
+        // `Connection: keep-alive` followed by `Connection: Upgrade`
+        let check_header_contains = |name, token, err| {
+            find_header(name)?;
+            if contains_token(headers, name, token) {
                 Ok(())
             } else {
                 Err(err)
             }
         };
 
-        check_header("Upgrade", "websocket", Error::UpgradeNotWebSocket)?;
+        check_header_contains("Upgrade", "websocket", Error::UpgradeNotWebSocket)?;
         check_header_contains("Connection", "Upgrade", Error::ConnectionNotUpgrade)?;
         check_header(
             "Sec-WebSocket-Version",
@@ -77,6 +80,11 @@ impl ClientRequest {
         )?;
 
         let key = find_header("Sec-WebSocket-Key")?;
+        // The key must be a base64-encoded 16-byte value
+        if !STANDARD.decode(key).is_ok_and(|key| key.len() == 16) {
+            return Err(Error::InvalidWebSocketKey);
+        }
+
         let ws_accept = digest(key.as_bytes());
         Ok(Self { ws_accept })
     }
@@ -89,8 +97,64 @@ impl ClientRequest {
     }
 }
 
+/// Writes header lines for all headers in a [`HeaderMap`] to a HTTP/1.1
+/// response.
+pub(crate) fn write_headers(resp: &mut Vec<u8>, headers: &HeaderMap) {
+    for name in headers.keys() {
+        let values = headers.get_all(name).iter();
+
+        if name == SET_COOKIE {
+            // Set-Cookie is treated differently because if multiple values are present,
+            // multiple header entries should be used rather than one
+            for value in values {
+                resp.extend_from_slice(name.as_str().as_bytes());
+                resp.extend_from_slice(b": ");
+                resp.extend_from_slice(value.as_bytes());
+                resp.extend_from_slice(b"\r\n");
+            }
+        } else {
+            // All other header values of the same key should be concatenated with a comma
+            resp.extend_from_slice(name.as_str().as_bytes());
+            resp.extend_from_slice(b": ");
+
+            let mut values = values.peekable();
+            while let Some(value) = values.next() {
+                resp.extend_from_slice(value.as_bytes());
+
+                if values.peek().is_some() {
+                    resp.push(b',');
+                }
+            }
+
+            resp.extend_from_slice(b"\r\n");
+        }
+    }
+}
+
+/// Selects the first of the server's subprotocols that is offered by the
+/// client in its `Sec-WebSocket-Protocol` headers.
+fn select_subprotocol<'a>(headers: &HeaderMap, supported: &'a [String]) -> Option<&'a str> {
+    let offered = || {
+        headers
+            .get_all(SEC_WEBSOCKET_PROTOCOL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+    };
+
+    supported
+        .iter()
+        .find(|subprotocol| offered().any(|offered| offered == subprotocol.as_str()))
+        .map(String::as_str)
+}
+
 /// A codec that implements a [`Decoder`] for HTTP/1.1 upgrade requests and
-/// yields the request and a HTTP/1.1 response to reply with.
+/// yields the request, the head of a HTTP/1.1 response to reply with and the
+/// selected subprotocol, if any.
+///
+/// The response head lacks the empty line terminating it, so that further
+/// headers can be added.
 ///
 /// It does not implement an [`Encoder`].
 ///
@@ -98,22 +162,108 @@ impl ClientRequest {
 pub struct Codec<'a> {
     /// List of headers to add to the Switching Protocols response.
     pub response_headers: &'a HeaderMap,
+    /// Subprotocols supported by the server, in order of preference.
+    pub subprotocols: &'a [String],
+    /// Whether to reject clients that do not offer any of the subprotocols.
+    pub require_subprotocol: bool,
+    /// Whether to echo the offered subprotocols if none of them is supported.
+    pub echo_all_subprotocols: bool,
+    /// Maximum length of the request head.
+    pub max_len: usize,
+}
+
+impl Codec<'_> {
+    /// Validates a parsed handshake request and builds the switching protocols
+    /// response for it, without the terminating empty line, along with the
+    /// selected subprotocol.
+    pub(crate) fn respond(
+        &self,
+        request: &http::Request<()>,
+    ) -> Result<(Vec<u8>, Option<String>), crate::Error> {
+        let (ws_accept, subprotocol) = self.validate(request)?;
+
+        // Preallocate the size without extra headers
+        let mut resp = Vec::with_capacity(SWITCHING_PROTOCOLS_BODY.len() + ws_accept.len() + 4);
+
+        resp.extend_from_slice(SWITCHING_PROTOCOLS_BODY);
+        resp.extend_from_slice(ws_accept.as_bytes());
+        resp.extend_from_slice(b"\r\n");
+
+        if let Some(subprotocol) = &subprotocol {
+            resp.extend_from_slice(b"Sec-WebSocket-Protocol: ");
+            resp.extend_from_slice(subprotocol.as_bytes());
+            resp.extend_from_slice(b"\r\n");
+        } else if let Some(offered) = self.echoed_subprotocols(request.headers()) {
+            resp.extend_from_slice(b"Sec-WebSocket-Protocol: ");
+            resp.extend_from_slice(offered.as_bytes());
+            resp.extend_from_slice(b"\r\n");
+        }
+
+        write_headers(&mut resp, self.response_headers);
+
+        Ok((resp, subprotocol))
+    }
+
+    /// Validates a parsed handshake request, returning the value of the
+    /// `Sec-WebSocket-Accept` header and the selected subprotocol.
+    pub(crate) fn validate(
+        &self,
+        request: &http::Request<()>,
+    ) -> Result<(String, Option<String>), crate::Error> {
+        let ws_accept = ClientRequest::parse(request.headers())?.ws_accept();
+
+        let subprotocol = select_subprotocol(request.headers(), self.subprotocols);
+        if subprotocol.is_none() && self.require_subprotocol {
+            return Err(Error::NoMatchingSubprotocol.into());
+        }
+
+        Ok((ws_accept, subprotocol.map(ToOwned::to_owned)))
+    }
+
+    /// Returns the subprotocols offered by the client to echo back if none was
+    /// selected and echoing them is enabled.
+    pub(crate) fn echoed_subprotocols(&self, headers: &HeaderMap) -> Option<http::HeaderValue> {
+        if !self.echo_all_subprotocols {
+            return None;
+        }
+
+        let mut offered = Vec::new();
+        for value in headers.get_all(SEC_WEBSOCKET_PROTOCOL) {
+            if !offered.is_empty() {
+                offered.extend_from_slice(b", ");
+            }
+            offered.extend_from_slice(value.as_bytes());
+        }
+
+        if offered.is_empty() {
+            None
+        } else {
+            http::HeaderValue::from_bytes(&offered).ok()
+        }
+    }
 }
 
 impl Decoder for Codec<'_> {
     type Error = crate::Error;
-    type Item = (http::Request<()>, Vec<u8>);
+    type Item = (http::Request<()>, Vec<u8>, Option<String>);
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let mut headers = [httparse::EMPTY_HEADER; 64];
+        let mut headers = [httparse::EMPTY_HEADER; MAX_REQUEST_HEADERS];
         let mut request = Request::new(&mut headers);
-        let status = request.parse(src).map_err(Error::Parsing)?;
+        let status = request.parse(src).map_err(Error::from)?;
 
         if !status.is_complete() {
-            return Ok(None);
+            return if src.len() > self.max_len {
+                Err(Error::HandshakeTooLarge.into())
+            } else {
+                Ok(None)
+            };
         }
 
         let request_len = status.unwrap();
+        if request_len > self.max_len {
+            return Err(Error::HandshakeTooLarge.into());
+        }
 
         let mut builder = http::request::Builder::new();
         if let Some(m) = request.method {
@@ -140,7 +290,7 @@ impl Decoder for Codec<'_> {
             let value = http::HeaderValue::from_bytes(header.value)
                 .map_err(|_| Error::Parsing(httparse::Error::HeaderValue))?;
 
-            header_map.insert(name, value);
+            header_map.append(name, value);
         }
 
         // You have to build the request before you can assign headers: https://github.com/hyperium/http/issues/91
@@ -149,51 +299,208 @@ impl Decoder for Codec<'_> {
             .expect("httparse sees the request as valid");
         *request.headers_mut() = header_map;
 
-        let ws_accept =
-            ClientRequest::parse(|name| request.headers().get(name).and_then(|h| h.to_str().ok()))?
-                .ws_accept();
-
+        let (response, subprotocol) = self.respond(&request)?;
         src.advance(request_len);
 
-        // Preallocate the size without extra headers
-        let mut resp = Vec::with_capacity(SWITCHING_PROTOCOLS_BODY.len() + ws_accept.len() + 4);
+        Ok(Some((request, response, subprotocol)))
+    }
+}
 
-        resp.extend_from_slice(SWITCHING_PROTOCOLS_BODY);
-        resp.extend_from_slice(ws_accept.as_bytes());
-        resp.extend_from_slice(b"\r\n");
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use http::HeaderMap;
+    use tokio_util::codec::Decoder;
+
+    use super::Codec;
+    use crate::upgrade::Error;
+
+    /// Builds an upgrade request with the sample key from RFC 6455, section
+    /// 1.3, replacing the header lines starting with the names of the given
+    /// headers.
+    fn request(replaced_headers: &str) -> BytesMut {
+        let mut request = String::from("GET /chat HTTP/1.1\r\nHost: example.com\r\n");
+
+        for line in [
+            "Upgrade: websocket\r\n",
+            "Connection: Upgrade\r\n",
+            "Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n",
+            "Sec-WebSocket-Version: 13\r\n",
+        ] {
+            let name = &line[..line.find(':').unwrap()];
+            if !replaced_headers
+                .split("\r\n")
+                .any(|replaced| replaced.starts_with(name))
+            {
+                request.push_str(line);
+            }
+        }
 
-        for name in self.response_headers.keys() {
-            let values = self.response_headers.get_all(name).iter();
-
-            if name == SET_COOKIE {
-                // Set-Cookie is treated differently because if multiple values are present,
-                // multiple header entries should be used rather than one
-                for value in values {
-                    resp.extend_from_slice(name.as_str().as_bytes());
-                    resp.extend_from_slice(b": ");
-                    resp.extend_from_slice(value.as_bytes());
-                    resp.extend_from_slice(b"\r\n");
-                }
-            } else {
-                // All other header values of the same key should be concatenated with a comma
-                resp.extend_from_slice(name.as_str().as_bytes());
-                resp.extend_from_slice(b": ");
+        request.push_str(replaced_headers);
+        request.push_str("\r\n");
 
-                let mut values = values.peekable();
-                while let Some(value) = values.next() {
-                    resp.extend_from_slice(value.as_bytes());
+        BytesMut::from(request.as_bytes())
+    }
 
-                    if values.peek().is_some() {
-                        resp.push(b',');
-                    }
-                }
+    /// Decodes a request with a codec that adds no response headers and
+    /// supports no subprotocols.
+    fn decode(
+        request: &mut BytesMut,
+    ) -> Result<Option<<Codec<'static> as Decoder>::Item>, crate::Error> {
+        Codec {
+            response_headers: &HeaderMap::new(),
+            subprotocols: &[],
+            require_subprotocol: false,
+            echo_all_subprotocols: false,
+            max_len: crate::upgrade::DEFAULT_MAX_HANDSHAKE_LEN,
+        }
+        .decode(request)
+    }
 
-                resp.extend_from_slice(b"\r\n");
+    #[test]
+    fn accept_key() {
+        let (request, response, _) = decode(&mut request("")).unwrap().unwrap();
+
+        assert_eq!(request.uri(), "/chat");
+        assert!(
+            String::from_utf8(response)
+                .unwrap()
+                .contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n")
+        );
+    }
+
+    #[test]
+    fn incomplete_request() {
+        let mut request = BytesMut::from(&request("")[..20]);
+        assert!(decode(&mut request).unwrap().is_none());
+        assert_eq!(request.len(), 20);
+    }
+
+    #[test]
+    fn too_many_headers() {
+        // The request already contains five headers
+        let headers = "X-Header: a\r\n".repeat(59);
+        assert!(decode(&mut request(&headers)).unwrap().is_some());
+
+        let headers = "X-Header: a\r\n".repeat(60);
+        assert!(matches!(
+            decode(&mut request(&headers)),
+            Err(crate::Error::Upgrade(Error::HandshakeTooLarge))
+        ));
+    }
+
+    #[test]
+    fn repeated_connection_header() {
+        let mut request = request("Connection: keep-alive\r\nConnection: Upgrade\r\n");
+        let (request, _, _) = decode(&mut request).unwrap().unwrap();
+
+        assert_eq!(request.headers().get_all("connection").iter().count(), 2);
+    }
+
+    #[test]
+    fn token_lists() {
+        for headers in [
+            "Upgrade: WebSocket\r\nConnection: keep-alive, UPGRADE\r\n",
+            "Upgrade: h2c, websocket\r\nConnection: upgrade,keep-alive\r\n",
+            "Upgrade: h2c\r\nUpgrade: websocket\r\nConnection: Keep-Alive\r\nConnection: \
+             Upgrade\r\n",
+        ] {
+            assert!(
+                decode(&mut request(headers)).unwrap().is_some(),
+                "{headers:?}"
+            );
+        }
+
+        // Tokens are not matched partially
+        for (headers, expected) in [
+            ("Upgrade: websockets\r\n", Error::UpgradeNotWebSocket),
+            ("Connection: upgraded\r\n", Error::ConnectionNotUpgrade),
+        ] {
+            match decode(&mut request(headers)) {
+                Err(crate::Error::Upgrade(e)) => assert_eq!(e.to_string(), expected.to_string()),
+                other => panic!("unexpected result for {headers:?}: {other:?}"),
             }
         }
+    }
 
-        resp.extend_from_slice(b"\r\n");
+    #[test]
+    fn invalid_requests() {
+        for (headers, expected) in [
+            ("Upgrade: h2c\r\n", "upgrade header value was not websocket"),
+            (
+                "Connection: keep-alive\r\n",
+                "connection header value was not upgrade",
+            ),
+            (
+                "Sec-WebSocket-Version: 8\r\n",
+                "unsupported WebSocket version",
+            ),
+            (
+                "Sec-WebSocket-Key: c2hvcnQ=\r\n",
+                "invalid Sec-WebSocket-Key header",
+            ),
+            (
+                "Sec-WebSocket-Key: not base64!\r\n",
+                "invalid Sec-WebSocket-Key header",
+            ),
+        ] {
+            match decode(&mut request(headers)) {
+                Err(crate::Error::Upgrade(e)) => assert_eq!(e.to_string(), expected),
+                other => panic!("unexpected result for {headers:?}: {other:?}"),
+            }
+        }
+
+        let mut request = BytesMut::from(
+            "GET /chat HTTP/1.1\r\nHost: example.com\r\nUpgrade: websocket\r\nConnection: \
+             Upgrade\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        );
+        assert!(matches!(
+            decode(&mut request),
+            Err(crate::Error::Upgrade(Error::MissingHeader(
+                "Sec-WebSocket-Key"
+            )))
+        ));
+    }
+
+    #[test]
+    fn subprotocol_selection() {
+        let supported = ["mqtt".to_owned(), "graphql-ws".to_owned()];
+        let headers = HeaderMap::new();
+        let codec = |require_subprotocol| Codec {
+            response_headers: &headers,
+            subprotocols: &supported,
+            require_subprotocol,
+            echo_all_subprotocols: false,
+            max_len: crate::upgrade::DEFAULT_MAX_HANDSHAKE_LEN,
+        };
 
-        Ok(Some((request, resp)))
+        let mut offered =
+            request("Sec-WebSocket-Protocol: wamp, graphql-ws\r\nSec-WebSocket-Protocol: mqtt\r\n");
+        let (_, response, subprotocol) = codec(false).decode(&mut offered).unwrap().unwrap();
+        assert_eq!(subprotocol.as_deref(), Some("mqtt"));
+        assert!(
+            String::from_utf8(response)
+                .unwrap()
+                .contains("\r\nSec-WebSocket-Protocol: mqtt\r\n")
+        );
+
+        let mut not_offered = request("Sec-WebSocket-Protocol: wamp\r\n");
+        let (_, response, subprotocol) = codec(false).decode(&mut not_offered).unwrap().unwrap();
+        assert_eq!(subprotocol, None);
+        assert!(
+            !String::from_utf8(response)
+                .unwrap()
+                .contains("Sec-WebSocket-Protocol")
+        );
+
+        let mut not_offered = request("Sec-WebSocket-Protocol: wamp\r\n");
+        assert!(matches!(
+            codec(true).decode(&mut not_offered),
+            Err(crate::Error::Upgrade(Error::NoMatchingSubprotocol))
+        ));
+
+        let mut offered = request("Sec-WebSocket-Protocol: graphql-ws\r\n");
+        let (_, _, subprotocol) = codec(true).decode(&mut offered).unwrap().unwrap();
+        assert_eq!(subprotocol.as_deref(), Some("graphql-ws"));
     }
 }
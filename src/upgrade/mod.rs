@@ -1,9 +1,23 @@
 //! HTTP upgrade request and response generation and validation helpers.
 
 use std::fmt;
+#[cfg(any(feature = "client", feature = "server"))]
+use std::{future::poll_fn, pin::Pin};
+
+use bytes::Bytes;
+#[cfg(any(feature = "client", feature = "server"))]
+use bytes::BytesMut;
+#[cfg(any(feature = "client", feature = "server"))]
+use futures_core::Stream;
+#[cfg(any(feature = "client", feature = "server"))]
+use tokio::io::AsyncRead;
+#[cfg(any(feature = "client", feature = "server"))]
+use tokio_util::codec::{Decoder, FramedRead};
 #[cfg(feature = "server")]
 pub(crate) mod client_request;
 #[cfg(feature = "client")]
+pub(crate) mod proxy_response;
+#[cfg(feature = "client")]
 pub(crate) mod server_response;
 
 /// A parsed HTTP/1.1 101 Switching Protocols response.
@@ -11,6 +25,74 @@ pub(crate) mod server_response;
 #[cfg(feature = "client")]
 pub type Response = http::Response<()>;
 
+/// Default maximum length of a handshake request or response head.
+#[cfg(any(feature = "client", feature = "server"))]
+pub(crate) const DEFAULT_MAX_HANDSHAKE_LEN: usize = 64 * 1024;
+
+/// Maximum number of headers in the head of a server's or proxy's response.
+#[cfg(feature = "client")]
+pub(crate) const MAX_RESPONSE_HEADERS: usize = 25;
+
+/// Maximum number of headers in the head of a client's request.
+#[cfg(feature = "server")]
+pub(crate) const MAX_REQUEST_HEADERS: usize = 64;
+
+/// Reads the head of a HTTP request or response, decoding bytes that were
+/// already read from the stream before reading more.
+///
+/// [`FramedRead`] only decodes its buffer after reading from the stream, so a
+/// prefix containing the whole head would otherwise wait for more data.
+#[cfg(any(feature = "client", feature = "server"))]
+pub(crate) async fn read_head<S, D>(
+    framed: &mut FramedRead<S, D>,
+    prefix: &[u8],
+) -> Option<Result<D::Item, D::Error>>
+where
+    S: AsyncRead + Unpin,
+    D: Decoder,
+{
+    if !prefix.is_empty() {
+        let mut buffer = BytesMut::from(prefix);
+        let decoded = framed.decoder_mut().decode(&mut buffer);
+        framed.read_buffer_mut().extend_from_slice(&buffer);
+
+        match decoded {
+            Ok(Some(item)) => return Some(Ok(item)),
+            Ok(None) => {}
+            Err(e) => return Some(Err(e)),
+        }
+    }
+
+    poll_fn(|cx| Pin::new(&mut *framed).poll_next(cx)).await
+}
+
+/// Returns whether a string is a valid HTTP token as defined in
+/// [RFC 9110](https://datatracker.ietf.org/doc/html/rfc9110#name-tokens).
+#[cfg(any(feature = "client", feature = "server"))]
+pub(crate) fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value.bytes().all(|b| {
+            b.is_ascii_alphanumeric()
+                || matches!(
+                    b,
+                    b'!' | b'#'
+                        | b'$'
+                        | b'%'
+                        | b'&'
+                        | b'\''
+                        | b'*'
+                        | b'+'
+                        | b'-'
+                        | b'.'
+                        | b'^'
+                        | b'_'
+                        | b'`'
+                        | b'|'
+                        | b'~'
+                )
+        })
+}
+
 /// Errors that occur during the HTTP upgrade handshake between client and
 /// server.
 #[derive(Debug)]
@@ -25,13 +107,33 @@ pub enum Error {
     /// `Sec-WebSocket-Version` header sent by the client is not supported by
     /// the server.
     UnsupportedWebSocketVersion,
+    /// `Sec-WebSocket-Key` header sent by the client is not a base64-encoded
+    /// 16-byte value.
+    InvalidWebSocketKey,
     /// Failed to parse client request or server response.
     Parsing(httparse::Error),
-    /// Server did not return a HTTP Switching Protocols response.
-    DidNotSwitchProtocols(u16),
+    /// Server did not return a HTTP Switching Protocols response. Contains the
+    /// server's response, including its body if its length was specified via
+    /// the `Content-Length` header and does not exceed 64 KiB.
+    DidNotSwitchProtocols(Box<http::Response<Bytes>>),
     /// Server returned a `Sec-WebSocket-Accept` that is not compatible with the
     /// `Sec-WebSocket-Key` sent by the client.
     WrongWebSocketAccept,
+    /// Server selected a `Sec-WebSocket-Protocol` that was not offered by the
+    /// client.
+    InvalidSubprotocol,
+    /// Client did not offer any of the subprotocols supported by the server,
+    /// while the server requires one.
+    NoMatchingSubprotocol,
+    /// Handshake request or response exceeded the maximum length before its
+    /// head was complete, or its head contained more headers than supported.
+    /// Requests may contain up to 64 headers, responses up to 25.
+    HandshakeTooLarge,
+    /// Server rejected the client's request with the contained status code.
+    Rejected(u16),
+    /// HTTP proxy did not return a successful response to the `CONNECT`
+    /// request.
+    ProxyConnectFailed(u16),
 }
 
 impl fmt::Display for Error {
@@ -44,12 +146,26 @@ impl fmt::Display for Error {
             Error::UpgradeNotWebSocket => f.write_str("upgrade header value was not websocket"),
             Error::ConnectionNotUpgrade => f.write_str("connection header value was not upgrade"),
             Error::UnsupportedWebSocketVersion => f.write_str("unsupported WebSocket version"),
+            Error::InvalidWebSocketKey => f.write_str("invalid Sec-WebSocket-Key header"),
             Error::Parsing(e) => e.fmt(f),
-            Error::DidNotSwitchProtocols(status) => {
+            Error::DidNotSwitchProtocols(response) => {
                 f.write_str("expected HTTP 101 Switching Protocols, got status code ")?;
-                f.write_fmt(format_args!("{status}"))
+                f.write_fmt(format_args!("{}", response.status().as_u16()))
             }
             Error::WrongWebSocketAccept => f.write_str("mismatching Sec-WebSocket-Accept header"),
+            Error::InvalidSubprotocol => f.write_str("server selected a subprotocol not offered"),
+            Error::NoMatchingSubprotocol => {
+                f.write_str("client did not offer a supported subprotocol")
+            }
+            Error::HandshakeTooLarge => f.write_str("handshake exceeded the maximum length"),
+            Error::Rejected(status) => {
+                f.write_str("handshake request was rejected with status code ")?;
+                f.write_fmt(format_args!("{status}"))
+            }
+            Error::ProxyConnectFailed(status) => {
+                f.write_str("proxy failed to establish tunnel, got status code ")?;
+                f.write_fmt(format_args!("{status}"))
+            }
         }
     }
 }
@@ -61,8 +177,14 @@ impl std::error::Error for Error {
             | Error::UpgradeNotWebSocket
             | Error::ConnectionNotUpgrade
             | Error::UnsupportedWebSocketVersion
+            | Error::InvalidWebSocketKey
             | Error::DidNotSwitchProtocols(_)
-            | Error::WrongWebSocketAccept => None,
+            | Error::ProxyConnectFailed(_)
+            | Error::Rejected(_)
+            | Error::HandshakeTooLarge
+            | Error::WrongWebSocketAccept
+            | Error::InvalidSubprotocol
+            | Error::NoMatchingSubprotocol => None,
             Error::Parsing(e) => Some(e),
         }
     }
@@ -70,6 +192,11 @@ impl std::error::Error for Error {
 
 impl From<httparse::Error> for Error {
     fn from(err: httparse::Error) -> Self {
-        Self::Parsing(err)
+        // Running out of space for headers is a limit of the head's size rather
+        // than malformed input
+        match err {
+            httparse::Error::TooManyHeaders => Self::HandshakeTooLarge,
+            err => Self::Parsing(err),
+        }
     }
 }
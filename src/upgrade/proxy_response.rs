@@ -0,0 +1,48 @@
+//! A [`Codec`] to parse a HTTP proxy's response to a `CONNECT` request.
+use bytes::{Buf, BytesMut};
+use httparse::Response;
+use tokio_util::codec::Decoder;
+
+use crate::upgrade::{Error, MAX_RESPONSE_HEADERS};
+
+/// [`Decoder`] for parsing the proxy's response to the client's HTTP `CONNECT`
+/// request.
+///
+/// It yields once the proxy established the tunnel.
+pub struct Codec {
+    /// Maximum length of the response head.
+    pub max_len: usize,
+}
+
+impl Decoder for Codec {
+    type Error = crate::Error;
+    type Item = ();
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let mut headers = [httparse::EMPTY_HEADER; MAX_RESPONSE_HEADERS];
+        let mut response = Response::new(&mut headers);
+        let status = response.parse(src).map_err(Error::from)?;
+
+        if !status.is_complete() {
+            return if src.len() > self.max_len {
+                Err(Error::HandshakeTooLarge.into())
+            } else {
+                Ok(None)
+            };
+        }
+
+        let response_len = status.unwrap();
+        if response_len > self.max_len {
+            return Err(Error::HandshakeTooLarge.into());
+        }
+        let code = response.code.unwrap();
+
+        if !(200..300).contains(&code) {
+            return Err(crate::Error::Upgrade(Error::ProxyConnectFailed(code)));
+        }
+
+        src.advance(response_len);
+
+        Ok(Some(()))
+    }
+}
@@ -1,18 +1,49 @@
 //! A [`Codec`] to perform a HTTP Upgrade handshake with a server and validate
 //! the response.
-use std::str::FromStr;
+use std::str::{self, FromStr};
 
 use base64::{Engine, engine::general_purpose::STANDARD};
 use bytes::{Buf, BytesMut};
-use http::{HeaderValue, StatusCode, header::HeaderName};
+use http::{
+    HeaderValue, StatusCode, Uri,
+    header::{CONTENT_LENGTH, HeaderName},
+};
 use httparse::{Header, Response};
 use tokio_util::codec::Decoder;
 
-use crate::{sha::digest, upgrade::Error};
+use crate::{
+    sha::digest,
+    upgrade::{Error, MAX_RESPONSE_HEADERS},
+};
 
 /// HTTP status code for Switching Protocols.
 const SWITCHING_PROTOCOLS: u16 = 101;
 
+/// Maximum length of the body of a response that rejects the upgrade to read.
+/// Longer bodies are discarded.
+const MAX_REJECTION_BODY_LEN: usize = 64 * 1024;
+
+/// Resolves the value of a `Location` header relative to the URI of the
+/// request it was sent in response to.
+pub(crate) fn resolve_location(base: &Uri, location: &str) -> Option<Uri> {
+    let location = Uri::from_str(location).ok()?;
+
+    if location.scheme().is_some() {
+        return Some(location);
+    }
+
+    // Only absolute paths are supported as relative references
+    let path_and_query = location.path_and_query()?;
+    if !path_and_query.as_str().starts_with('/') {
+        return None;
+    }
+
+    let mut parts = base.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.clone());
+
+    Uri::from_parts(parts).ok()
+}
+
 /// Find a header in an array of headers by name, ignoring ASCII case.
 fn header<'a, 'header: 'a>(
     headers: &'a [Header<'header>],
@@ -28,52 +59,118 @@ fn header<'a, 'header: 'a>(
 
 /// [`Decoder`] for parsing the server's response to the client's HTTP
 /// `Connection: Upgrade` request.
-pub struct Codec {
+pub struct Codec<'a> {
     /// The SHA-1 digest of the `Sec-WebSocket-Key` header.
     ws_accept: [u8; 20],
+    /// The subprotocols offered to the server via the `Sec-WebSocket-Protocol`
+    /// header.
+    subprotocols: &'a [String],
+    /// URI of the request if redirect responses are yielded, used to resolve
+    /// their `Location` header.
+    redirect_base: Option<&'a Uri>,
+    /// Maximum length of the response head.
+    max_len: usize,
 }
 
-impl Codec {
+impl<'a> Codec<'a> {
     /// Returns a new [`Codec`].
     ///
     /// The `key` parameter provides the string passed to the server via the
-    /// HTTP `Sec-WebSocket-Key` header.
+    /// HTTP `Sec-WebSocket-Key` header, `subprotocols` the list of
+    /// subprotocols offered via the `Sec-WebSocket-Protocol` header.
     #[must_use]
-    pub fn new(key: &[u8]) -> Self {
+    pub fn new(key: &[u8], subprotocols: &'a [String]) -> Self {
         Self {
             ws_accept: digest(key),
+            subprotocols,
+            redirect_base: None,
+            max_len: super::DEFAULT_MAX_HANDSHAKE_LEN,
+        }
+    }
+
+    /// Sets the URI of the request if redirect responses with a `Location`
+    /// header that resolves relative to it are yielded instead of failing with
+    /// [`Error::DidNotSwitchProtocols`].
+    #[must_use]
+    pub fn follow_redirects(mut self, base: Option<&'a Uri>) -> Self {
+        self.redirect_base = base;
+
+        self
+    }
+
+    /// Sets the maximum length of the response head, longer responses fail with
+    /// [`Error::HandshakeTooLarge`].
+    #[must_use]
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+
+        self
+    }
+
+    /// Validates the headers of a Switching Protocols response.
+    fn validate(&self, headers: &[Header<'_>]) -> Result<(), Error> {
+        let ws_accept_header = header(headers, "Sec-WebSocket-Accept")?;
+        let mut ws_accept = [0; 20];
+        // Overlong values fail to decode into the buffer, short ones are caught by
+        // checking the decoded length
+        let ws_accept_len = STANDARD
+            .decode_slice(ws_accept_header, &mut ws_accept)
+            .map_err(|_| Error::WrongWebSocketAccept)?;
+
+        if ws_accept_len != ws_accept.len() || self.ws_accept != ws_accept {
+            return Err(Error::WrongWebSocketAccept);
+        }
+
+        // The server may only select one of the subprotocols we offered, if any
+        if let Ok(subprotocol) = header(headers, "Sec-WebSocket-Protocol")
+            && !self
+                .subprotocols
+                .iter()
+                .any(|offered| offered.as_bytes() == subprotocol)
+        {
+            return Err(Error::InvalidSubprotocol);
         }
+
+        Ok(())
     }
 }
 
-impl Decoder for Codec {
+impl Decoder for Codec<'_> {
     type Error = crate::Error;
     type Item = super::Response;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let mut headers = [httparse::EMPTY_HEADER; 25];
+        let mut headers = [httparse::EMPTY_HEADER; MAX_RESPONSE_HEADERS];
         let mut response = Response::new(&mut headers);
-        let status = response.parse(src).map_err(Error::Parsing)?;
+        let status = response.parse(src).map_err(Error::from)?;
 
         if !status.is_complete() {
-            return Ok(None);
+            return if src.len() > self.max_len {
+                Err(Error::HandshakeTooLarge.into())
+            } else {
+                Ok(None)
+            };
         }
 
         let response_len = status.unwrap();
+        if response_len > self.max_len {
+            return Err(Error::HandshakeTooLarge.into());
+        }
         let code = response.code.unwrap();
 
-        if code != SWITCHING_PROTOCOLS {
-            return Err(crate::Error::Upgrade(Error::DidNotSwitchProtocols(code)));
-        }
+        // Redirects are yielded to the caller to follow them, responses that
+        // cannot be followed fail with their body like other rejections
+        let is_redirect = (300..400).contains(&code)
+            && self.redirect_base.is_some_and(|base| {
+                header(response.headers, "Location")
+                    .ok()
+                    .and_then(|location| str::from_utf8(location).ok())
+                    .and_then(|location| resolve_location(base, location))
+                    .is_some()
+            });
 
-        let ws_accept_header = header(response.headers, "Sec-WebSocket-Accept")?;
-        let mut ws_accept = [0; 20];
-        STANDARD
-            .decode_slice_unchecked(ws_accept_header, &mut ws_accept)
-            .map_err(|_| Error::WrongWebSocketAccept)?;
-
-        if self.ws_accept != ws_accept {
-            return Err(crate::Error::Upgrade(Error::WrongWebSocketAccept));
+        if code == SWITCHING_PROTOCOLS {
+            self.validate(response.headers)?;
         }
 
         let mut parsed_response = http::Response::new(());
@@ -96,7 +193,31 @@ impl Decoder for Codec {
             let value = HeaderValue::from_bytes(header.value)
                 .map_err(|_| Error::Parsing(httparse::Error::HeaderValue))?;
 
-            header_map.insert(name, value);
+            header_map.append(name, value);
+        }
+
+        if code != SWITCHING_PROTOCOLS && !is_redirect {
+            // Only read bodies of a known and reasonable length, the connection is
+            // dropped afterwards anyways
+            let body_len = parsed_response
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok()?.parse::<usize>().ok())
+                .filter(|&len| len <= MAX_REJECTION_BODY_LEN)
+                .unwrap_or(0);
+
+            if src.len() < response_len + body_len {
+                src.reserve(response_len + body_len - src.len());
+
+                return Ok(None);
+            }
+
+            src.advance(response_len);
+            let body = src.split_to(body_len).freeze();
+
+            return Err(
+                Error::DidNotSwitchProtocols(Box::new(parsed_response.map(|()| body))).into(),
+            );
         }
 
         src.advance(response_len);
@@ -104,3 +225,202 @@ impl Decoder for Codec {
         Ok(Some(parsed_response))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use http::Uri;
+    use tokio_util::codec::Decoder;
+
+    use super::Codec;
+    use crate::upgrade::Error;
+
+    /// Sample key from RFC 6455, section 1.3.
+    const KEY: &[u8] = b"dGhlIHNhbXBsZSBub25jZQ==";
+
+    /// Builds a Switching Protocols response accepting [`KEY`] with the given
+    /// extra headers.
+    fn response(extra_headers: &str) -> BytesMut {
+        response_with_accept(
+            "Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n",
+            extra_headers,
+        )
+    }
+
+    /// Builds a Switching Protocols response with the given accept header
+    /// line and extra headers.
+    fn response_with_accept(accept: &str, extra_headers: &str) -> BytesMut {
+        BytesMut::from(
+            format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: \
+                 Upgrade\r\n{accept}{extra_headers}\r\n"
+            )
+            .as_bytes(),
+        )
+    }
+
+    #[test]
+    fn accept_validation() {
+        let mut codec = Codec::new(KEY, &[]);
+        assert!(codec.decode(&mut response("")).unwrap().is_some());
+
+        let mut codec = Codec::new(b"AQIDBAUGBwgJCgsMDQ4PEC==", &[]);
+        assert!(matches!(
+            codec.decode(&mut response("")),
+            Err(crate::Error::Upgrade(Error::WrongWebSocketAccept))
+        ));
+
+        let mut codec = Codec::new(KEY, &[]);
+        assert!(matches!(
+            codec.decode(&mut response_with_accept("", "")),
+            Err(crate::Error::Upgrade(Error::MissingHeader(
+                "Sec-WebSocket-Accept"
+            )))
+        ));
+
+        for accept in [
+            "s3pPLMBiTxaQ9kYG",
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOos3pPLMBiTxaQ9kYGzzhZRbK+xOo=",
+        ] {
+            let mut codec = Codec::new(KEY, &[]);
+            assert!(matches!(
+                codec.decode(&mut response_with_accept(
+                    &format!("Sec-WebSocket-Accept: {accept}\r\n"),
+                    ""
+                )),
+                Err(crate::Error::Upgrade(Error::WrongWebSocketAccept))
+            ));
+        }
+    }
+
+    #[test]
+    fn subprotocol_negotiation() {
+        let offered = ["graphql-ws".to_owned(), "mqtt".to_owned()];
+
+        let mut codec = Codec::new(KEY, &offered);
+        let res = codec
+            .decode(&mut response("Sec-WebSocket-Protocol: mqtt\r\n"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.headers()["sec-websocket-protocol"], "mqtt");
+
+        let mut codec = Codec::new(KEY, &offered);
+        assert!(codec.decode(&mut response("")).unwrap().is_some());
+
+        let mut codec = Codec::new(KEY, &offered);
+        assert!(matches!(
+            codec.decode(&mut response("Sec-WebSocket-Protocol: wamp\r\n")),
+            Err(crate::Error::Upgrade(Error::InvalidSubprotocol))
+        ));
+
+        let mut codec = Codec::new(KEY, &[]);
+        assert!(matches!(
+            codec.decode(&mut response("Sec-WebSocket-Protocol: mqtt\r\n")),
+            Err(crate::Error::Upgrade(Error::InvalidSubprotocol))
+        ));
+    }
+
+    #[test]
+    fn redirects() {
+        let redirect = "HTTP/1.1 302 Found\r\nLocation: ws://example.com/\r\n\r\n";
+
+        let mut codec = Codec::new(KEY, &[]);
+        assert!(matches!(
+            codec.decode(&mut BytesMut::from(redirect)),
+            Err(crate::Error::Upgrade(Error::DidNotSwitchProtocols(res))) if res.status() == 302
+        ));
+
+        let base = Uri::from_static("ws://example.org/");
+        let mut codec = Codec::new(KEY, &[]).follow_redirects(Some(&base));
+        let res = codec
+            .decode(&mut BytesMut::from(redirect))
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.status(), 302);
+        assert_eq!(res.headers()["location"], "ws://example.com/");
+
+        // Redirects that cannot be followed keep their body
+        for unusable in ["", "Location: relative\r\n"] {
+            let redirect =
+                format!("HTTP/1.1 302 Found\r\n{unusable}Content-Length: 5\r\n\r\nmoved");
+            let mut codec = Codec::new(KEY, &[]).follow_redirects(Some(&base));
+            let Err(crate::Error::Upgrade(Error::DidNotSwitchProtocols(res))) =
+                codec.decode(&mut BytesMut::from(redirect.as_str()))
+            else {
+                panic!("expected the upgrade to be rejected");
+            };
+            assert_eq!(res.body(), "moved");
+        }
+    }
+
+    #[test]
+    fn duplicate_headers_preserved() {
+        let mut codec = Codec::new(KEY, &[]);
+        let res = codec
+            .decode(&mut response(
+                "Set-Cookie: session=abc\r\nSet-Cookie: route=1\r\n",
+            ))
+            .unwrap()
+            .unwrap();
+
+        let cookies: Vec<_> = res.headers().get_all("set-cookie").iter().collect();
+        assert_eq!(cookies, ["session=abc", "route=1"]);
+    }
+
+    #[test]
+    fn rejection() {
+        let rejection = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 30\r\nContent-Length: \
+                         11\r\n\r\nslow down!\n";
+
+        // The body is awaited before failing
+        let mut codec = Codec::new(KEY, &[]);
+        let mut incomplete = BytesMut::from(&rejection[..rejection.len() - 1]);
+        assert!(codec.decode(&mut incomplete).unwrap().is_none());
+
+        let Err(crate::Error::Upgrade(Error::DidNotSwitchProtocols(res))) =
+            codec.decode(&mut BytesMut::from(rejection))
+        else {
+            panic!("expected the upgrade to be rejected");
+        };
+        assert_eq!(res.status(), 429);
+        assert_eq!(res.headers()["retry-after"], "30");
+        assert_eq!(res.body(), "slow down!\n");
+
+        let unknown_length = "HTTP/1.1 403 Forbidden\r\n\r\nforbidden";
+        let mut codec = Codec::new(KEY, &[]);
+        let Err(crate::Error::Upgrade(Error::DidNotSwitchProtocols(res))) =
+            codec.decode(&mut BytesMut::from(unknown_length))
+        else {
+            panic!("expected the upgrade to be rejected");
+        };
+        assert_eq!(res.status(), 403);
+        assert!(res.body().is_empty());
+    }
+
+    #[test]
+    fn too_large() {
+        let mut codec = Codec::new(KEY, &[]).max_len(64);
+        let mut partial =
+            BytesMut::from("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n");
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+
+        partial.extend_from_slice(b"Connection: Upgrade\r\n");
+        assert!(matches!(
+            codec.decode(&mut partial),
+            Err(crate::Error::Upgrade(Error::HandshakeTooLarge))
+        ));
+
+        let mut codec = Codec::new(KEY, &[]).max_len(64);
+        assert!(matches!(
+            codec.decode(&mut response("")),
+            Err(crate::Error::Upgrade(Error::HandshakeTooLarge))
+        ));
+
+        // The response already contains three headers
+        let headers = "X-Header: a\r\n".repeat(23);
+        assert!(matches!(
+            Codec::new(KEY, &[]).decode(&mut response(&headers)),
+            Err(crate::Error::Upgrade(Error::HandshakeTooLarge))
+        ));
+    }
+}
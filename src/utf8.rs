@@ -14,9 +14,23 @@ pub fn parse_str(input: &[u8]) -> Result<&str, ProtocolError> {
     simdutf8::basic::from_utf8(input).map_err(|_| ProtocolError::InvalidUtf8)
 }
 
-/// A streaming UTF-8 validator.
+/// A streaming UTF-8 validator that uses SIMD acceleration if available.
+///
+/// Input can be fed to the validator in chunks of arbitrary size, codepoints
+/// may be split across chunks. Up to the last three bytes of a chunk that form
+/// an incomplete codepoint are buffered and validated together with the start
+/// of the next chunk.
+///
+/// ```
+/// use tokio_websockets::Utf8Validator;
+///
+/// let mut validator = Utf8Validator::new();
+/// // "é" is encoded as 0xC3 0xA9
+/// assert!(validator.feed(b"caf\xC3", false).is_ok());
+/// assert!(validator.feed(b"\xA9", true).is_ok());
+/// ```
 #[derive(Debug)]
-pub(crate) struct Validator {
+pub struct Validator {
     /// Buffer for a partial codepoint. This is four bytes large to copy the
     /// missing bytes into the buffer and reuse the allocation.
     partial_codepoint: [u8; 4],
@@ -26,7 +40,7 @@ pub(crate) struct Validator {
 
 impl Validator {
     /// Creates a new validator.
-    #[cfg(any(feature = "client", feature = "server"))]
+    #[must_use]
     pub fn new() -> Self {
         Self {
             partial_codepoint: [0; 4],
@@ -53,16 +67,26 @@ impl Validator {
         }
     }
 
-    /// Resets the validator state.
+    /// Resets the validator state, discarding any buffered incomplete
+    /// codepoint. This allows reusing the validator for a new input, e.g. after
+    /// an error.
     #[inline]
     pub fn reset(&mut self) {
         self.partial_codepoint_len = 0;
     }
 
-    /// Feeds bytes into the streaming validator. Returns `Ok` if the input is
-    /// valid UTF-8, if `is_complete` is true, even if the input has incomplete
-    /// codepoints. Subsequent calls will validate incomplete codepoints
-    /// unless [`Self::reset`] is called in between.
+    /// Feeds bytes into the streaming validator.
+    ///
+    /// If `is_complete` is false, more input is expected to follow and an
+    /// incomplete codepoint at the end of the input is buffered to be validated
+    /// by the next call, unless [`Self::reset`] is called in between. If
+    /// `is_complete` is true, the input must end with a complete codepoint and
+    /// the validator is reset afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProtocolError::InvalidUtf8`] if the input is invalid UTF-8 or
+    /// incomplete despite `is_complete` being true.
     pub fn feed(&mut self, input: &[u8], is_complete: bool) -> Result<(), ProtocolError> {
         // If we have a partial codepoint, complete it
         let remaining_bytes = if self.partial_codepoint_len == 0 {
@@ -152,3 +176,9 @@ impl Validator {
         }
     }
 }
+
+impl Default for Validator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "server")]
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    time::timeout,
+};
+use tokio_websockets::{Config, Message, ServerBuilder};
+
+#[tokio::test]
+async fn flush_threshold() {
+    let (mut client, server) = tokio::io::duplex(1024);
+    let mut server = ServerBuilder::new()
+        .config(Config::default().flush_threshold(4096))
+        .serve(server);
+    let message = Message::binary(vec![0; 1024]);
+
+    // Each frame takes up 1028 bytes and the duplex only fits 1024 of them, so
+    // feeding stops at four or five queued frames
+    let mut queued = 0;
+    while timeout(Duration::from_millis(50), server.feed(message.clone()))
+        .await
+        .is_ok()
+    {
+        queued += 1;
+        assert!(queued <= 5, "queue exceeded the flush threshold");
+    }
+    assert!(queued >= 4);
+
+    // Reading makes room for the queue to be written
+    tokio::spawn(async move { tokio::io::copy(&mut client, &mut tokio::io::sink()).await });
+    server.feed(message).await.unwrap();
+    server.flush().await.unwrap();
+}
+
+#[tokio::test]
+async fn pong_before_queued_data() {
+    let (mut client, server) = tokio::io::duplex(1024);
+    let mut server = ServerBuilder::new()
+        .config(Config::default().frame_size(1024))
+        .serve(server);
+
+    // The message is queued as 16 frames
+    server
+        .feed(Message::binary(vec![0; 16 * 1024]))
+        .await
+        .unwrap();
+    // Masked ping with a 4 byte payload
+    client
+        .write_all(&[0x89, 0x84, 0, 0, 0, 0, b'p', b'i', b'n', b'g'])
+        .await
+        .unwrap();
+    assert!(server.next().await.unwrap().unwrap().is_ping());
+
+    // The pong is sent right after the first frame, which was already being
+    // written when the ping arrived
+    let mut buf = vec![0; 16 * 1028 + 6];
+    let (flushed, read) = tokio::join!(server.flush(), client.read_exact(&mut buf));
+    flushed.unwrap();
+    read.unwrap();
+
+    assert_eq!(buf[..4], [0x02, 126, 0x04, 0x00]);
+    assert_eq!(buf[1028..1034], [0x8A, 0x04, b'p', b'i', b'n', b'g']);
+}
@@ -3,11 +3,15 @@ use std::{
     io,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use futures_util::{FutureExt, StreamExt, task::noop_waker_ref};
-use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
-use tokio_websockets::ServerBuilder;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio_websockets::{Error, ServerBuilder};
+
+/// Masked ping frame with [`TO_SEND`] as its payload.
+const PING: &[u8] = b"\x89\x84\xe47D\xa4\xe55G\xa0";
 
 struct SuperSlow {
     buf: Vec<u8>,
@@ -16,7 +20,7 @@ struct SuperSlow {
 
 impl SuperSlow {
     pub fn new() -> Self {
-        let buf = b"\x89\x84\xe47D\xa4\xe55G\xa0".to_vec();
+        let buf = PING.to_vec();
 
         Self { buf, delays: 0 }
     }
@@ -91,3 +95,43 @@ async fn test_cancellation_safety() {
         }
     }
 }
+
+#[tokio::test]
+async fn recv_timeout_resumes() {
+    let (mut client, server) = tokio::io::duplex(1024);
+    let mut server = ServerBuilder::new().serve(server);
+
+    client.write_all(&PING[..4]).await.unwrap();
+    assert!(matches!(
+        server.recv_timeout(Duration::from_millis(20)).await,
+        Err(Error::Timeout)
+    ));
+
+    client.write_all(&PING[4..]).await.unwrap();
+    let msg = server
+        .recv_timeout(Duration::from_secs(1))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(msg.is_ping());
+    assert_eq!(&*msg.into_payload(), TO_SEND);
+}
+
+#[tokio::test]
+async fn dropped_next_resumes() {
+    // A text message fragmented into "he" and "llo", masked with a zero key
+    const FRAMES: &[u8] = b"\x01\x82\0\0\0\0he\x80\x83\0\0\0\0llo";
+
+    let (mut client, server) = tokio::io::duplex(1024);
+    let mut server = ServerBuilder::new().serve(server);
+    let mut cx = Context::from_waker(noop_waker_ref());
+
+    // The first frame and part of the second one are received before the
+    // future is dropped
+    client.write_all(&FRAMES[..11]).await.unwrap();
+    assert!(server.next().poll_unpin(&mut cx).is_pending());
+
+    client.write_all(&FRAMES[11..]).await.unwrap();
+    let msg = server.next().await.unwrap().unwrap();
+    assert_eq!(msg.as_text(), Some("hello"));
+}
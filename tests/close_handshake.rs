@@ -0,0 +1,98 @@
+#![cfg(all(feature = "client", feature = "server"))]
+use std::{io, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use tokio_websockets::{ClientBuilder, CloseCode, Config, Error, Message, ServerBuilder};
+
+#[tokio::test]
+async fn close_with_reason() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new().take_over(client);
+    let mut server = ServerBuilder::new().serve(server);
+
+    let server = tokio::spawn(async move {
+        let msg = server.next().await.unwrap().unwrap();
+        assert!(server.next().await.is_none());
+
+        msg
+    });
+
+    client
+        .close_with(CloseCode::GOING_AWAY, "bye")
+        .await
+        .unwrap();
+    assert!(matches!(
+        client.send(Message::text("late")).await,
+        Err(Error::AlreadyClosed)
+    ));
+
+    let msg = server.await.unwrap();
+    assert_eq!(msg.as_close(), Some((CloseCode::GOING_AWAY, "bye")));
+}
+
+#[tokio::test]
+async fn close_without_code() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new().take_over(client);
+    let mut server = ServerBuilder::new().serve(server);
+
+    client.send(Message::close(None, "")).await.unwrap();
+
+    // The close message is yielded before the stream ends
+    let msg = server.next().await.unwrap().unwrap();
+    assert_eq!(msg.as_close(), Some((CloseCode::NO_STATUS_RECEIVED, "")));
+    assert!(server.next().await.is_none());
+
+    // The close frame was echoed
+    let msg = client.next().await.unwrap().unwrap();
+    assert!(msg.is_close());
+    assert!(client.next().await.is_none());
+}
+
+#[tokio::test]
+async fn close_with_timeout() {
+    // The remote never acknowledges the close frame
+    let (client, _server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new()
+        .config(Config::default().close_timeout(Duration::from_millis(50)))
+        .take_over(client);
+
+    client
+        .close_with(CloseCode::NORMAL_CLOSURE, "")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn close_with_invalid() {
+    let (client, _server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new().take_over(client);
+
+    let reason = "a".repeat(124);
+    assert!(matches!(
+        client.close_with(CloseCode::NORMAL_CLOSURE, &reason).await,
+        Err(Error::PayloadTooLong { len: 124, .. })
+    ));
+    assert!(matches!(
+        client.close_with(CloseCode::NO_STATUS_RECEIVED, "").await,
+        Err(Error::Protocol(_))
+    ));
+}
+
+#[tokio::test]
+async fn unexpected_eof() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut server = ServerBuilder::new().serve(server);
+    let mut client = ClientBuilder::new().take_over(client);
+
+    client.send(Message::text("hello")).await.unwrap();
+    drop(client);
+
+    let msg = server.next().await.unwrap().unwrap();
+    assert_eq!(msg.as_text(), Some("hello"));
+    assert!(matches!(
+        server.next().await,
+        Some(Err(Error::Io(e))) if e.kind() == io::ErrorKind::UnexpectedEof
+    ));
+    assert!(server.next().await.is_none());
+}
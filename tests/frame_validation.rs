@@ -0,0 +1,161 @@
+#![cfg(feature = "server")]
+use futures_util::StreamExt;
+#[cfg(feature = "client")]
+use tokio_websockets::ClientBuilder;
+use tokio_websockets::{Error, ServerBuilder, proto::ProtocolError};
+
+/// Feeds frames to a server and returns the error they cause along with the
+/// status code of the close frame sent in response.
+async fn reject(frames: &[u8]) -> (Error, u16) {
+    let mut written = Vec::new();
+    let mut server = ServerBuilder::new().serve(tokio::io::join(frames, &mut written));
+
+    let error = loop {
+        match server.next().await {
+            Some(Ok(_)) => {}
+            Some(Err(e)) => break e,
+            None => panic!("stream ended without an error"),
+        }
+    };
+    assert!(server.next().await.is_none());
+    drop(server);
+
+    assert_eq!(written[0], 0x88, "expected a close frame");
+    (error, u16::from_be_bytes([written[2], written[3]]))
+}
+
+#[tokio::test]
+async fn reserved_bits() {
+    for rsv in [0x40, 0x20, 0x10] {
+        // Masked, final text frame with an empty payload
+        let (error, code) = reject(&[0x81 | rsv, 0x80, 0, 0, 0, 0]).await;
+
+        assert!(matches!(error, Error::Protocol(ProtocolError::InvalidRsv)));
+        assert_eq!(code, 1002);
+    }
+}
+
+#[tokio::test]
+async fn unmasked_frame() {
+    // Unmasked, final text frame with an empty payload
+    let (error, code) = reject(&[0x81, 0x00]).await;
+
+    assert!(matches!(
+        error,
+        Error::Protocol(ProtocolError::UnexpectedUnmaskedFrame)
+    ));
+    assert_eq!(code, 1002);
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn masked_frame() {
+    let frames: &[u8] = &[0x81, 0x80, 0, 0, 0, 0];
+    let mut written = Vec::new();
+    let mut client = ClientBuilder::new().take_over(tokio::io::join(frames, &mut written));
+
+    assert!(matches!(
+        client.next().await,
+        Some(Err(Error::Protocol(ProtocolError::UnexpectedMaskedFrame)))
+    ));
+    assert!(client.next().await.is_none());
+    drop(client);
+
+    // The close frame sent by the client is masked
+    assert_eq!(written[0], 0x88);
+    assert_eq!(written[1] >> 7, 1);
+    let code = u16::from_be_bytes([written[6] ^ written[2], written[7] ^ written[3]]);
+    assert_eq!(code, 1002);
+}
+
+#[tokio::test]
+async fn overlong_control_frame() {
+    // Ping with a 126 byte payload
+    let mut frame = vec![0x89, 0xFE, 0, 126, 0, 0, 0, 0];
+    frame.resize(frame.len() + 126, 0);
+    let (error, code) = reject(&frame).await;
+
+    assert!(matches!(
+        error,
+        Error::Protocol(ProtocolError::InvalidPayloadLength)
+    ));
+    assert_eq!(code, 1002);
+}
+
+#[tokio::test]
+async fn fragmented_control_frame() {
+    // Unfinished close frame with status code 1000
+    let (error, code) = reject(&[0x08, 0x82, 0, 0, 0, 0, 0x03, 0xE8]).await;
+
+    assert!(matches!(
+        error,
+        Error::Protocol(ProtocolError::FragmentedControlFrame)
+    ));
+    assert_eq!(code, 1002);
+}
+
+#[tokio::test]
+async fn reserved_opcodes() {
+    for opcode in (0x3..=0x7).chain(0xB..=0xF) {
+        let (error, code) = reject(&[0x80 | opcode, 0x80, 0, 0, 0, 0]).await;
+
+        assert!(matches!(
+            error,
+            Error::Protocol(ProtocolError::InvalidOpcode)
+        ));
+        assert_eq!(code, 1002);
+    }
+}
+
+#[tokio::test]
+async fn unfinished_message_interrupted() {
+    // Unfinished text frame followed by another text frame
+    let (error, code) = reject(&[0x01, 0x80, 0, 0, 0, 0, 0x81, 0x80, 0, 0, 0, 0]).await;
+
+    assert!(matches!(
+        error,
+        Error::Protocol(ProtocolError::InvalidOpcode)
+    ));
+    assert_eq!(code, 1002);
+}
+
+#[tokio::test]
+async fn lone_continuation_frame() {
+    let (error, code) = reject(&[0x80, 0x80, 0, 0, 0, 0]).await;
+
+    assert!(matches!(
+        error,
+        Error::Protocol(ProtocolError::InvalidOpcode)
+    ));
+    assert_eq!(code, 1002);
+}
+
+#[tokio::test]
+async fn interleaved_control_frame() {
+    #[rustfmt::skip]
+    let frames: &[u8] = &[
+        // Unfinished text frame
+        0x01, 0x81, 0, 0, 0, 0, b'a',
+        // Ping in between
+        0x89, 0x80, 0, 0, 0, 0,
+        // Final continuation frame
+        0x80, 0x81, 0, 0, 0, 0, b'b',
+    ];
+    let mut server = ServerBuilder::new().serve(tokio::io::join(frames, tokio::io::sink()));
+
+    assert!(server.next().await.unwrap().unwrap().is_ping());
+    let msg = server.next().await.unwrap().unwrap();
+    assert_eq!(msg.as_text(), Some("ab"));
+}
+
+#[tokio::test]
+async fn one_byte_close_frame() {
+    // Masked close frame with a payload too short to hold a close code
+    let (error, code) = reject(&[0x88, 0x81, 0, 0, 0, 0, 0x03]).await;
+
+    assert!(matches!(
+        error,
+        Error::Protocol(ProtocolError::InvalidPayloadLength)
+    ));
+    assert_eq!(code, 1002);
+}
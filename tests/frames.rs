@@ -0,0 +1,106 @@
+#![cfg(all(feature = "client", feature = "server"))]
+use futures_util::{SinkExt, StreamExt};
+use tokio_websockets::{ClientBuilder, Error, Frame, Message, ServerBuilder};
+
+#[tokio::test]
+async fn send_and_receive_frames() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new().take_over(client);
+    let mut server = ServerBuilder::new().serve(server);
+
+    client.send_frame(Frame::text("hel", false)).await.unwrap();
+    client
+        .send_frame(Message::ping("ping").into())
+        .await
+        .unwrap();
+    client
+        .send_frame(Frame::continuation("lo", true))
+        .await
+        .unwrap();
+
+    // Frames are not reassembled
+    let frame = server.next_frame().await.unwrap().unwrap();
+    assert!(frame.is_text() && !frame.is_final());
+    assert_eq!(&**frame.as_payload(), b"hel");
+    let frame = server.next_frame().await.unwrap().unwrap();
+    assert!(frame.is_ping());
+    let frame = server.next_frame().await.unwrap().unwrap();
+    assert!(frame.is_continuation() && frame.is_final());
+    assert_eq!(&**frame.as_payload(), b"lo");
+
+    // Pings are still answered automatically
+    let msg = client.next().await.unwrap().unwrap();
+    assert!(msg.is_pong());
+
+    // The rest of a message started via frames is returned as binary
+    client.send_frame(Frame::text("ab", false)).await.unwrap();
+    client
+        .send_frame(Frame::continuation("cd", false))
+        .await
+        .unwrap();
+    client
+        .send_frame(Frame::continuation("ef", true))
+        .await
+        .unwrap();
+
+    let frame = server.next_frame().await.unwrap().unwrap();
+    assert_eq!(&*frame.into_payload(), b"ab");
+    let msg = server.next().await.unwrap().unwrap();
+    assert!(msg.is_binary());
+    assert_eq!(&**msg.as_payload(), b"cdef");
+}
+
+#[tokio::test]
+async fn unfinished_frames() {
+    let (client, _server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new().take_over(client);
+
+    client.send_frame(Frame::binary("ab", false)).await.unwrap();
+
+    assert!(matches!(
+        client.send(Message::text("text")).await,
+        Err(Error::FragmentationInProgress)
+    ));
+    assert!(matches!(
+        client.send_frame(Frame::text("text", true)).await,
+        Err(Error::FragmentationInProgress)
+    ));
+
+    client
+        .send_frame(Frame::continuation("cd", true))
+        .await
+        .unwrap();
+    client.send(Message::text("text")).await.unwrap();
+}
+
+#[tokio::test]
+async fn explicit_mask() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new().take_over(client);
+    let mut server = ServerBuilder::new().serve(server);
+
+    // The payload is sent as given, masked by the caller
+    let mask = [1, 2, 3, 4];
+    let masked: Vec<u8> = b"hello"
+        .iter()
+        .zip(mask.iter().cycle())
+        .map(|(byte, key)| byte ^ key)
+        .collect();
+    client
+        .send_frame_with_mask(Frame::binary(masked, true), Some(mask))
+        .await
+        .unwrap();
+
+    let frame = server.next_frame().await.unwrap().unwrap();
+    assert_eq!(&*frame.into_payload(), b"hello");
+
+    // Servers reject unmasked frames from clients
+    client
+        .send_frame_with_mask(Frame::binary("hello", true), None)
+        .await
+        .unwrap();
+    assert!(matches!(
+        server.next_frame().await,
+        Some(Err(Error::Protocol(_)))
+    ));
+}
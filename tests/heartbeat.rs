@@ -0,0 +1,67 @@
+#![cfg(all(feature = "client", feature = "server"))]
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::time::timeout;
+use tokio_websockets::{ClientBuilder, Frame, Message, ServerBuilder};
+
+#[tokio::test]
+async fn heartbeat() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new()
+        .take_over(client)
+        .with_heartbeat(Duration::from_millis(20), || Message::text("heartbeat"));
+    let mut server = ServerBuilder::new().serve(server);
+
+    let server = tokio::spawn(async move {
+        for _ in 0..2 {
+            let msg = server.next().await.unwrap().unwrap();
+            assert_eq!(msg.as_text(), Some("heartbeat"));
+        }
+        server.send(Message::text("done")).await.unwrap();
+
+        server
+    });
+
+    // Heartbeats are sent while waiting for messages
+    let msg = client.next().await.unwrap().unwrap();
+    assert_eq!(msg.as_text(), Some("done"));
+    let mut server = server.await.unwrap();
+
+    // No heartbeats are sent while a message is unfinished, otherwise the
+    // server would fail the connection
+    client
+        .get_mut()
+        .send_frame(Frame::text("unfinished", false))
+        .await
+        .unwrap();
+    assert!(
+        timeout(Duration::from_millis(50), client.next())
+            .await
+            .is_err()
+    );
+    client
+        .get_mut()
+        .send_frame(Frame::continuation("", true))
+        .await
+        .unwrap();
+
+    // Skip heartbeats sent before the message was started
+    let msg = loop {
+        let msg = server.next().await.unwrap().unwrap();
+        if msg.as_text() != Some("heartbeat") {
+            break msg;
+        }
+    };
+    assert_eq!(msg.as_text(), Some("unfinished"));
+
+    // Nor once the closing handshake has begun, which would fail with
+    // Error::AlreadyClosed
+    client.send(Message::close(None, "")).await.unwrap();
+    assert!(server.next().await.unwrap().unwrap().is_close());
+    assert!(
+        timeout(Duration::from_millis(50), client.next())
+            .await
+            .is_err()
+    );
+}
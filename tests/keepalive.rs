@@ -0,0 +1,54 @@
+#![cfg(all(feature = "client", feature = "server"))]
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use tokio_websockets::{ClientBuilder, Config, Error, ServerBuilder};
+
+/// Config with keepalive pings after 20ms of inactivity and a 20ms timeout.
+fn config() -> Config {
+    Config::default().keepalive(Duration::from_millis(20), Duration::from_millis(20))
+}
+
+#[tokio::test]
+async fn keepalive_timeout() {
+    // The remote never reads the ping, let alone responds to it
+    let (client, _server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new().config(config()).take_over(client);
+
+    assert!(matches!(
+        client.next().await,
+        Some(Err(Error::KeepaliveTimeout))
+    ));
+    assert!(client.next().await.is_none());
+}
+
+#[tokio::test]
+async fn keepalive_answered() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new().config(config()).take_over(client);
+    let mut server = ServerBuilder::new().serve(server);
+
+    let pings = tokio::spawn(async move {
+        let mut pings = Vec::new();
+        while let Some(Ok(msg)) = server.next().await {
+            assert!(msg.is_ping());
+            pings.push(msg.into_payload().to_vec());
+        }
+        pings
+    });
+
+    // The server answers the pings, so the client never times out
+    let pongs = async {
+        while let Some(msg) = client.next().await {
+            assert!(msg.unwrap().is_pong());
+        }
+    };
+    let result = tokio::time::timeout(Duration::from_millis(200), pongs).await;
+    assert!(result.is_err());
+    drop(client);
+
+    // Each ping carries a different token
+    let pings = pings.await.unwrap();
+    assert!(pings.len() >= 2);
+    assert_ne!(pings[0], pings[1]);
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "server")]
+use futures_util::StreamExt;
+use tokio_websockets::{Error, Limits, ServerBuilder};
+
+#[tokio::test]
+async fn fragmented_message_too_long() {
+    #[rustfmt::skip]
+    let fragments: &[u8] = &[
+        // Unfinished text frame with a 3 byte payload
+        0x01, 0x83, 0, 0, 0, 0, b'a', b'a', b'a',
+        // Final continuation frame with a 3 byte payload
+        0x80, 0x83, 0, 0, 0, 0, b'a', b'a', b'a',
+    ];
+    let mut written = Vec::new();
+
+    let mut server = ServerBuilder::new()
+        .limits(Limits::default().max_payload_len(Some(4)))
+        .serve(tokio::io::join(fragments, &mut written));
+
+    assert!(matches!(
+        server.next().await,
+        Some(Err(Error::PayloadTooLong { len: 6, max_len: 4 }))
+    ));
+    assert!(server.next().await.is_none());
+    drop(server);
+
+    // Close frame with status code 1009 (message too big)
+    assert_eq!(written[..4], [0x88, 15, 0x03, 0xF1]);
+    assert_eq!(&written[4..], b"max length: 4");
+}
+
+#[tokio::test]
+async fn frame_too_long() {
+    // Header of a text frame announcing a 1 MiB payload that is never sent
+    let header: &[u8] = &[0x81, 0xFF, 0, 0, 0, 0, 0, 0x10, 0, 0];
+    let mut written = Vec::new();
+
+    let mut server = ServerBuilder::new()
+        .limits(Limits::default().max_frame_len(Some(1024)))
+        .serve(tokio::io::join(header, &mut written));
+
+    assert!(matches!(
+        server.next().await,
+        Some(Err(Error::FrameTooLong {
+            len: 1_048_576,
+            max_len: 1024
+        }))
+    ));
+    assert!(server.next().await.is_none());
+    drop(server);
+
+    // Close frame with status code 1009 (message too big)
+    assert_eq!(written[..4], [0x88, 18, 0x03, 0xF1]);
+}
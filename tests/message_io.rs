@@ -0,0 +1,146 @@
+#![cfg(all(feature = "client", feature = "server"))]
+use futures_util::{SinkExt, StreamExt};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_websockets::{
+    ClientBuilder, CloseCode, Config, Error, Message, ServerBuilder, proto::MessageKind,
+};
+
+#[tokio::test]
+async fn message_reader() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new().take_over(client);
+    let mut server = ServerBuilder::new()
+        .config(Config::default().frame_size(4))
+        .serve(server);
+
+    // Each message is sent as three frames
+    server
+        .feed(Message::binary(&b"0123456789"[..]))
+        .await
+        .unwrap();
+    server
+        .send(Message::binary(&b"0123456789"[..]))
+        .await
+        .unwrap();
+
+    let mut reader = client.message_reader();
+    let mut buf = [0; 6];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(reader.is_text(), Some(false));
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).await.unwrap();
+    assert_eq!(&buf, b"012345");
+    assert_eq!(rest, b"6789");
+
+    // The rest of the first frame is discarded when dropping the reader early
+    let mut reader = client.message_reader();
+    let mut buf = [0; 2];
+    reader.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"01");
+    drop(reader);
+
+    let msg = client.next().await.unwrap().unwrap();
+    assert!(msg.is_binary());
+    assert_eq!(&*msg.into_payload(), b"456789");
+}
+
+#[tokio::test]
+async fn message_writer() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new().take_over(client);
+    let mut server = ServerBuilder::new().serve(server);
+
+    // Codepoints may be split across writes
+    let mut writer = client.message_writer(MessageKind::Text);
+    for byte in "héllo".as_bytes() {
+        writer.write_all(&[*byte]).await.unwrap();
+    }
+    writer.shutdown().await.unwrap();
+    drop(writer);
+
+    let msg = server.next().await.unwrap().unwrap();
+    assert_eq!(msg.as_text(), Some("héllo"));
+
+    let mut writer = client.message_writer(MessageKind::Text);
+    let result = writer.write_all(&[0xFF]).await;
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+
+    // Dropping an unfinished writer closes the connection
+    writer.write_all(b"unfinished").await.unwrap();
+    drop(writer);
+    client.flush().await.unwrap();
+
+    let msg = server.next().await.unwrap().unwrap();
+    assert_eq!(
+        msg.as_close(),
+        Some((
+            CloseCode::INTERNAL_SERVER_ERROR,
+            "message was not completed"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn message_writer_interleaved() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new().take_over(client);
+    let mut server = ServerBuilder::new().serve(server);
+
+    // Leaking an unfinished writer leaves its message in progress
+    let mut writer = client.message_writer(MessageKind::Text);
+    writer.write_all(b"text").await.unwrap();
+    std::mem::forget(writer);
+
+    assert!(matches!(
+        client.send(Message::binary(&b"binary"[..])).await,
+        Err(Error::FragmentationInProgress)
+    ));
+
+    let mut writer = client.message_writer(MessageKind::Binary);
+    let error = writer.write_all(b"binary").await.unwrap_err();
+    assert!(matches!(
+        error.into_inner().unwrap().downcast::<Error>().as_deref(),
+        Ok(Error::FragmentationInProgress)
+    ));
+    drop(writer);
+
+    // Control frames may still be interleaved
+    client.send(Message::ping("ping")).await.unwrap();
+    assert!(server.next().await.unwrap().unwrap().is_ping());
+}
+
+#[tokio::test]
+async fn into_text() {
+    let (client, server) = tokio::io::duplex(1024);
+    let mut client = ClientBuilder::new().take_over(client);
+    let mut server = ServerBuilder::new().serve(server);
+
+    client.send(Message::text("hello")).await.unwrap();
+    client.send(Message::binary(&b"world"[..])).await.unwrap();
+
+    let text = server.next().await.unwrap().unwrap();
+    assert_eq!(text.into_text().unwrap(), "hello");
+
+    let binary = server.next().await.unwrap().unwrap();
+    let binary = binary.into_text().unwrap_err();
+    assert_eq!(binary.into_bytes(), &b"world"[..]);
+}
+
+#[test]
+fn formatting() {
+    assert_eq!(Message::text("hello").to_string(), "Text(5 bytes)");
+    assert_eq!(
+        Message::binary(vec![0; 1024]).to_string(),
+        "Binary(1024 bytes)"
+    );
+    assert_eq!(
+        Message::close(Some(CloseCode::NORMAL_CLOSURE), "bye").to_string(),
+        "Close(1000, \"bye\")"
+    );
+    assert_eq!(Message::close(None, "").to_string(), "Close(1005, \"\")");
+
+    // Long payloads are truncated
+    let debug = format!("{:?}", Message::text("a".repeat(1024)));
+    assert!(debug.contains(&format!("b\"{}\", 1024 bytes", "a".repeat(32))));
+    assert!(!debug.contains(&"a".repeat(33)));
+}
@@ -0,0 +1,112 @@
+#![cfg(all(feature = "client", feature = "server"))]
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::io::AsyncWriteExt;
+use tokio_websockets::{ClientBuilder, Error, Frame, Message, ServerBuilder, proto::Observer};
+
+/// Observer counting the events it is notified of.
+#[derive(Debug, Default)]
+struct Counter {
+    handshakes: AtomicUsize,
+    failed_handshakes: AtomicUsize,
+    frames_sent: AtomicUsize,
+    bytes_sent: AtomicUsize,
+    pongs_sent: AtomicUsize,
+    frames_received: AtomicUsize,
+    protocol_errors: AtomicUsize,
+}
+
+impl Counter {
+    fn get(counter: &AtomicUsize) -> usize {
+        counter.load(Ordering::Relaxed)
+    }
+}
+
+impl Observer for Counter {
+    fn handshake(&self, result: Result<(), &Error>) {
+        match result {
+            Ok(()) => self.handshakes.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.failed_handshakes.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    fn frame_sent(&self, frame: &Frame) {
+        self.frames_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent
+            .fetch_add(frame.as_payload().len(), Ordering::Relaxed);
+
+        if frame.is_pong() {
+            self.pongs_sent.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn frame_received(&self, _frame: &Frame) {
+        self.frames_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn protocol_error(&self, _error: &Error) {
+        self.protocol_errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[tokio::test]
+async fn frames_and_handshake() {
+    let client_counter = Arc::new(Counter::default());
+    let server_counter = Arc::new(Counter::default());
+    let client_builder = ClientBuilder::new()
+        .uri("ws://localhost/")
+        .unwrap()
+        .observer(client_counter.clone());
+    let server_builder = ServerBuilder::new().observer(server_counter.clone());
+
+    let (client, server) = tokio::io::duplex(1024);
+    let (client, server) = tokio::join!(
+        client_builder.connect_on(client),
+        server_builder.accept(server)
+    );
+    let (mut client, _) = client.unwrap();
+    let (_, mut server) = server.unwrap();
+
+    assert_eq!(Counter::get(&client_counter.handshakes), 1);
+    assert_eq!(Counter::get(&server_counter.handshakes), 1);
+
+    client.send(Message::text("hello")).await.unwrap();
+    client.send(Message::ping("ping")).await.unwrap();
+    assert!(server.next().await.unwrap().unwrap().is_text());
+    assert!(server.next().await.unwrap().unwrap().is_ping());
+    server.flush().await.unwrap();
+
+    assert_eq!(Counter::get(&client_counter.frames_sent), 2);
+    assert_eq!(Counter::get(&client_counter.bytes_sent), 9);
+    assert_eq!(Counter::get(&server_counter.frames_received), 2);
+    assert_eq!(Counter::get(&server_counter.pongs_sent), 1);
+    assert_eq!(Counter::get(&server_counter.protocol_errors), 0);
+}
+
+#[tokio::test]
+async fn failures() {
+    let counter = Arc::new(Counter::default());
+    let builder = ServerBuilder::new().observer(counter.clone());
+
+    let mut written = Vec::new();
+    let result = builder
+        .accept(tokio::io::join(
+            &b"GET / HTTP/1.1\r\n\r\n"[..],
+            &mut written,
+        ))
+        .await;
+    assert!(result.is_err());
+    assert_eq!(Counter::get(&counter.failed_handshakes), 1);
+
+    // Frames sent by clients must be masked
+    let (mut client, server) = tokio::io::duplex(1024);
+    let mut server = builder.serve(server);
+    client.write_all(b"\x81\x00").await.unwrap();
+
+    assert!(matches!(server.next().await, Some(Err(Error::Protocol(_)))));
+    assert_eq!(Counter::get(&counter.protocol_errors), 1);
+}
@@ -1,6 +1,6 @@
 #![cfg(all(feature = "client", feature = "server"))]
 
-use futures_util::{SinkExt, StreamExt, stream};
+use futures_util::{SinkExt, StreamExt, future, stream};
 use tokio::io::{AsyncRead, AsyncWrite, duplex};
 use tokio_websockets::{ClientBuilder, Message, ServerBuilder};
 
@@ -21,6 +21,7 @@ where
     let server = ServerBuilder::new().serve(stream);
 
     let messages = server
+        .filter(|message| future::ready(!message.as_ref().unwrap().is_close()))
         .inspect(|message| {
             let message = message.as_ref().unwrap();
             assert!(matches!(message.as_text(), Some(MESSAGE)));
@@ -38,4 +39,5 @@ where
     let mut client = ClientBuilder::new().take_over(stream);
     let mut messages = stream::iter((0..NUM_MSG).map(|_| Ok(Message::text(MESSAGE))));
     client.send_all(&mut messages).await.unwrap();
+    client.close().await.unwrap();
 }
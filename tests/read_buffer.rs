@@ -0,0 +1,31 @@
+#![cfg(feature = "server")]
+use futures_util::StreamExt;
+use tokio_websockets::{Config, ServerBuilder};
+
+#[tokio::test]
+async fn read_buffer_size() {
+    let mut frames = Vec::new();
+    for _ in 0..64 {
+        frames.extend_from_slice(&[0x82, 0x84, 0, 0, 0, 0, 1, 2, 3, 4]);
+    }
+    // A frame larger than the read buffer
+    frames.extend_from_slice(&[0x82, 0xfe, 0x04, 0x00, 0, 0, 0, 0]);
+    frames.extend_from_slice(&[5; 1024]);
+    frames.extend_from_slice(&[0x88, 0x80, 0, 0, 0, 0]);
+
+    for size in [0, 1, 1024 * 1024] {
+        let mut server = ServerBuilder::new()
+            .config(Config::default().read_buffer_size(size))
+            .serve(tokio::io::join(&frames[..], tokio::io::sink()));
+
+        for _ in 0..64 {
+            let msg = server.next().await.unwrap().unwrap();
+            assert_eq!(msg.as_payload().to_vec(), [1, 2, 3, 4]);
+        }
+
+        let msg = server.next().await.unwrap().unwrap();
+        assert_eq!(msg.as_payload().to_vec(), [5; 1024]);
+        assert!(server.next().await.unwrap().unwrap().is_close());
+        assert!(server.next().await.is_none());
+    }
+}
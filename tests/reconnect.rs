@@ -0,0 +1,58 @@
+#![cfg(all(feature = "client", feature = "server"))]
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio_websockets::{
+    ClientBuilder, Error, Message, ServerBuilder,
+    reconnect::{Event, ReconnectingStream},
+};
+
+#[tokio::test]
+async fn reconnect() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // Every connection is dropped after sending a single message
+    let server = tokio::spawn(async move {
+        for i in 0..2 {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (_, mut server) = ServerBuilder::new().accept(stream).await.unwrap();
+
+            let msg = server.next().await.unwrap().unwrap();
+            assert_eq!(msg.as_text(), Some("subscribe"));
+            server.send(Message::text(i.to_string())).await.unwrap();
+        }
+    });
+
+    let builder = ClientBuilder::new()
+        .uri(&format!("ws://127.0.0.1:{port}"))
+        .unwrap();
+    let mut client = ReconnectingStream::new(builder)
+        .backoff(Duration::from_millis(10), Duration::from_millis(20))
+        .max_retries(2)
+        .on_connect(|| vec![Message::text("subscribe")]);
+
+    for i in 0..2 {
+        assert!(matches!(client.next().await, Some(Ok(Event::Connected))));
+        assert!(client.is_connected());
+
+        let Some(Ok(Event::Message(msg))) = client.next().await else {
+            panic!("expected a message");
+        };
+        assert_eq!(msg.as_text(), Some(i.to_string().as_str()));
+
+        // The server did not complete the close handshake
+        assert!(matches!(client.next().await, Some(Err(Error::Io(_)))));
+    }
+
+    server.await.unwrap();
+
+    // The listener is gone, so reconnecting fails until giving up
+    assert!(matches!(client.next().await, Some(Err(_))));
+    assert!(client.next().await.is_none());
+    assert!(matches!(
+        client.send(Message::text("late")).await,
+        Err(Error::AlreadyClosed)
+    ));
+}
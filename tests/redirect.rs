@@ -0,0 +1,174 @@
+#![cfg(all(feature = "client", feature = "server"))]
+use futures_util::{SinkExt, StreamExt};
+use http::{HeaderValue, header};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use tokio_websockets::{ClientBuilder, Connector, Error, Message, ServerBuilder, upgrade};
+
+/// Accepts connections and redirects each of them to `location`.
+async fn redirect_to(listener: TcpListener, location: String) {
+    loop {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+
+        while !request.ends_with(b"\r\n\r\n") {
+            request.push(stream.read_u8().await.unwrap());
+        }
+
+        let response = format!("HTTP/1.1 302 Found\r\nLocation: {location}\r\n\r\n");
+        stream.write_all(response.as_bytes()).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn follow_redirect() {
+    let server_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server_listener.local_addr().unwrap();
+    let redirect_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let redirect_addr = redirect_listener.local_addr().unwrap();
+
+    tokio::spawn(redirect_to(
+        redirect_listener,
+        format!("ws://{server_addr}/chat"),
+    ));
+    let server = tokio::spawn(async move {
+        let (stream, _) = server_listener.accept().await.unwrap();
+        let (request, mut server) = ServerBuilder::new().accept(stream).await.unwrap();
+        let msg = server.next().await.unwrap().unwrap();
+        server.send(msg).await.unwrap();
+        while server.next().await.is_some() {}
+
+        request
+    });
+
+    let builder = ClientBuilder::new()
+        .uri(&format!("ws://{redirect_addr}/"))
+        .unwrap();
+
+    let result = builder.connect().await;
+    assert!(matches!(result, Err(Error::Upgrade(_))));
+
+    let (mut client, _) = builder.max_redirects(1).connect().await.unwrap();
+    client.send(Message::text("Hello!")).await.unwrap();
+    let msg = client.next().await.unwrap().unwrap();
+    assert_eq!(msg.as_text(), Some("Hello!"));
+    client.close().await.unwrap();
+
+    let request = server.await.unwrap();
+    assert_eq!(request.uri(), "/chat");
+}
+
+#[tokio::test]
+async fn too_many_redirects() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // Redirect to the same listener again and again
+    tokio::spawn(redirect_to(listener, "/again".to_owned()));
+
+    let result = ClientBuilder::new()
+        .uri(&format!("ws://{addr}/"))
+        .unwrap()
+        .max_redirects(3)
+        .connect()
+        .await;
+    assert!(matches!(result, Err(Error::TooManyRedirects)));
+}
+
+#[tokio::test]
+async fn cross_origin_redirect() {
+    let server_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server_listener.local_addr().unwrap();
+    let redirect_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let redirect_addr = redirect_listener.local_addr().unwrap();
+
+    // A different port is a different origin
+    tokio::spawn(redirect_to(
+        redirect_listener,
+        format!("ws://{server_addr}/chat"),
+    ));
+    let server = tokio::spawn(async move {
+        let (stream, _) = server_listener.accept().await.unwrap();
+        let (request, _) = ServerBuilder::new().accept(stream).await.unwrap();
+
+        request
+    });
+
+    ClientBuilder::new()
+        .uri(&format!("ws://{redirect_addr}/"))
+        .unwrap()
+        .add_header(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer secret"),
+        )
+        .unwrap()
+        .add_header(header::COOKIE, HeaderValue::from_static("session=secret"))
+        .unwrap()
+        .add_header(header::USER_AGENT, HeaderValue::from_static("test"))
+        .unwrap()
+        .host_header("example.com")
+        .unwrap()
+        .max_redirects(1)
+        .connect()
+        .await
+        .unwrap();
+
+    let request = server.await.unwrap();
+    let headers = request.headers();
+    assert!(!headers.contains_key(header::AUTHORIZATION));
+    assert!(!headers.contains_key(header::COOKIE));
+    assert_eq!(headers[header::USER_AGENT], "test");
+    assert_eq!(headers[header::HOST], server_addr.to_string());
+}
+
+#[tokio::test]
+async fn insecure_redirect() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(redirect_to(listener, format!("ws://{addr}/")));
+
+    // The plain connector lets the wss URI be served without a TLS handshake
+    let result = ClientBuilder::new()
+        .uri(&format!("wss://{addr}/"))
+        .unwrap()
+        .connector(&Connector::Plain)
+        .max_redirects(1)
+        .connect()
+        .await;
+    assert!(matches!(result, Err(Error::InsecureRedirect)));
+}
+
+#[tokio::test]
+async fn redirect_without_location() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+
+        while !request.ends_with(b"\r\n\r\n") {
+            request.push(stream.read_u8().await.unwrap());
+        }
+
+        stream
+            .write_all(b"HTTP/1.1 302 Found\r\nContent-Length: 5\r\n\r\nmoved")
+            .await
+            .unwrap();
+    });
+
+    let result = ClientBuilder::new()
+        .uri(&format!("ws://{addr}/"))
+        .unwrap()
+        .max_redirects(1)
+        .connect()
+        .await;
+    let Err(Error::Upgrade(upgrade::Error::DidNotSwitchProtocols(res))) = result else {
+        panic!("expected the upgrade to be rejected");
+    };
+    assert_eq!(res.status(), 302);
+    assert_eq!(res.body(), "moved");
+}
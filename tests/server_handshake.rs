@@ -0,0 +1,246 @@
+#![cfg(feature = "server")]
+use http::{HeaderMap, HeaderValue, StatusCode, header};
+use tokio_websockets::{Error, ServerBuilder, server::Rejection, upgrade};
+
+/// Upgrade request with the sample key from RFC 6455, section 1.3.
+const REQUEST: &[u8] = b"GET /chat HTTP/1.1\r\nHost: example.com\r\nUpgrade: \
+    websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: \
+    dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\nAuthorization: Bearer \
+    secret\r\n\r\n";
+
+/// Accepts [`REQUEST`] with a builder and returns the result alongside the
+/// response written by the server.
+async fn accept(builder: ServerBuilder) -> (Result<(), Error>, String) {
+    let mut written = Vec::new();
+    let result = builder
+        .accept(tokio::io::join(REQUEST, &mut written))
+        .await
+        .map(drop);
+
+    (result, String::from_utf8(written).unwrap())
+}
+
+#[tokio::test]
+async fn response_headers() {
+    let builder = ServerBuilder::new()
+        .add_header(header::SET_COOKIE, HeaderValue::from_static("a=1"))
+        .unwrap()
+        .append_header(header::SET_COOKIE, HeaderValue::from_static("b=2"))
+        .unwrap()
+        .add_header(header::SERVER, HeaderValue::from_static("first"))
+        .unwrap()
+        .add_header(header::SERVER, HeaderValue::from_static("second"))
+        .unwrap();
+
+    let (result, response) = accept(builder).await;
+
+    assert!(result.is_ok());
+    assert!(response.contains("\r\nset-cookie: a=1\r\nset-cookie: b=2\r\n"));
+    assert!(response.contains("\r\nserver: second\r\n"));
+    assert!(!response.contains("first"));
+}
+
+#[tokio::test]
+async fn request_approved() {
+    let builder = ServerBuilder::new().on_request(|request| {
+        assert_eq!(request.uri(), "/chat");
+        assert_eq!(request.headers()[header::AUTHORIZATION], "Bearer secret");
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-user", HeaderValue::from_static("alice"));
+        Ok(headers)
+    });
+
+    let (result, response) = accept(builder).await;
+
+    assert!(result.is_ok());
+    assert!(response.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+    assert!(response.ends_with("\r\nx-user: alice\r\n\r\n"));
+}
+
+#[tokio::test]
+async fn request_rejected() {
+    let builder = ServerBuilder::new().on_request(|_| {
+        Err(Rejection::new(StatusCode::UNAUTHORIZED)
+            .header(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"))
+            .body("invalid token"))
+    });
+
+    let (result, response) = accept(builder).await;
+
+    assert!(matches!(
+        result,
+        Err(Error::Upgrade(upgrade::Error::Rejected(401)))
+    ));
+    assert_eq!(
+        response,
+        "HTTP/1.1 401 Unauthorized\r\nwww-authenticate: Bearer\r\ncontent-length: \
+         13\r\n\r\ninvalid token"
+    );
+}
+
+#[tokio::test]
+async fn disallowed_header_returned() {
+    let builder = ServerBuilder::new().on_request(|_| {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::UPGRADE, HeaderValue::from_static("h2c"));
+        Ok(headers)
+    });
+
+    let (result, response) = accept(builder).await;
+
+    assert!(matches!(result, Err(Error::DisallowedHeader)));
+    assert_eq!(response, "HTTP/1.1 500 Internal Server Error\r\n\r\n");
+}
+
+#[tokio::test]
+async fn request_prefix() {
+    // Part of the request was already read, e.g. to detect the protocol
+    for split in [REQUEST.len(), 16] {
+        let mut written = Vec::new();
+        let result = ServerBuilder::new()
+            .accept_with_prefix(
+                tokio::io::join(&REQUEST[split..], &mut written),
+                &REQUEST[..split],
+            )
+            .await;
+
+        assert_eq!(result.unwrap().0.uri(), "/chat");
+        assert!(written.starts_with(b"HTTP/1.1 101 Switching Protocols\r\n"));
+    }
+}
+
+#[tokio::test]
+async fn request_too_large() {
+    // The request head never ends
+    let mut request = b"GET /chat HTTP/1.1\r\n".to_vec();
+    for i in 0..32 {
+        request.extend_from_slice(format!("x-header-{i}: {}\r\n", "a".repeat(64)).as_bytes());
+    }
+    let mut written = Vec::new();
+
+    let result = ServerBuilder::new()
+        .max_handshake_len(1024)
+        .accept(tokio::io::join(&request[..], &mut written))
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(Error::Upgrade(upgrade::Error::HandshakeTooLarge))
+    ));
+    assert_eq!(written, b"HTTP/1.1 400 Bad Request\r\n\r\n");
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn unsupported_version() {
+    let (client, server) = tokio::io::duplex(4096);
+    let client_builder = tokio_websockets::ClientBuilder::new()
+        .uri("ws://localhost/")
+        .unwrap()
+        .version(8);
+    let server_builder = ServerBuilder::new();
+
+    let (client_result, server_result) = tokio::join!(
+        client_builder.connect_on(client),
+        server_builder.accept(server)
+    );
+
+    assert!(matches!(
+        server_result,
+        Err(Error::Upgrade(upgrade::Error::UnsupportedWebSocketVersion))
+    ));
+    let Err(Error::Upgrade(upgrade::Error::DidNotSwitchProtocols(response))) = client_result else {
+        panic!("client upgrade succeeded");
+    };
+    assert_eq!(response.status(), StatusCode::UPGRADE_REQUIRED);
+    assert_eq!(response.headers()[header::SEC_WEBSOCKET_VERSION], "13");
+}
+
+#[tokio::test]
+async fn parsed_request() {
+    let request = http::Request::get("/chat")
+        .header(header::HOST, "example.com")
+        .header(header::UPGRADE, "websocket")
+        .header(header::CONNECTION, "Upgrade")
+        .header(header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ==")
+        .header(header::SEC_WEBSOCKET_VERSION, "13")
+        .header(header::SEC_WEBSOCKET_PROTOCOL, "chat")
+        .body(())
+        .unwrap();
+    let builder = ServerBuilder::new()
+        .add_subprotocol("chat")
+        .add_header(header::SERVER, HeaderValue::from_static("test"))
+        .unwrap();
+
+    let response = builder.upgrade_response(&request).unwrap();
+    assert_eq!(response.status(), StatusCode::SWITCHING_PROTOCOLS);
+    assert_eq!(
+        response.headers()[header::SEC_WEBSOCKET_ACCEPT],
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+    );
+    assert_eq!(response.headers()[header::SEC_WEBSOCKET_PROTOCOL], "chat");
+    assert_eq!(response.headers()[header::SERVER], "test");
+
+    // The response was sent by the caller, so nothing is written
+    let mut written = Vec::new();
+    let stream = builder
+        .accept_from_request(&request, tokio::io::join(&b""[..], &mut written))
+        .unwrap();
+    assert_eq!(stream.subprotocol(), Some("chat"));
+    drop(stream);
+    assert!(written.is_empty());
+
+    let (mut parts, ()) = request.into_parts();
+    parts.headers.remove(header::SEC_WEBSOCKET_KEY);
+    let request = http::Request::from_parts(parts, ());
+    assert!(matches!(
+        builder.upgrade_response(&request),
+        Err(Error::Upgrade(upgrade::Error::MissingHeader(
+            "Sec-WebSocket-Key"
+        )))
+    ));
+    assert!(
+        builder
+            .accept_from_request(&request, tokio::io::empty())
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn echo_all_subprotocols() {
+    let request = [
+        &REQUEST[..REQUEST.len() - 2],
+        b"Sec-WebSocket-Protocol: mqtt, wamp\r\n\r\n",
+    ]
+    .concat();
+
+    for (builder, expected) in [
+        (ServerBuilder::new(), None),
+        (
+            ServerBuilder::new().echo_all_subprotocols(true),
+            Some("mqtt, wamp"),
+        ),
+        (
+            ServerBuilder::new()
+                .add_subprotocol("wamp")
+                .echo_all_subprotocols(true),
+            Some("wamp"),
+        ),
+    ] {
+        let mut written = Vec::new();
+        let (_, stream) = builder
+            .accept(tokio::io::join(&request[..], &mut written))
+            .await
+            .unwrap();
+        let selected = stream.subprotocol().map(ToOwned::to_owned);
+        drop(stream);
+
+        let response = String::from_utf8(written).unwrap();
+        let echoed = response
+            .lines()
+            .find_map(|line| line.strip_prefix("Sec-WebSocket-Protocol: "));
+        assert_eq!(echoed, expected);
+        assert_eq!(selected.as_deref(), expected.filter(|p| *p == "wamp"));
+    }
+}
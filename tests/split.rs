@@ -0,0 +1,53 @@
+#![cfg(all(feature = "client", feature = "server"))]
+use futures_util::{SinkExt, StreamExt};
+use tokio_websockets::{ClientBuilder, Message, ServerBuilder};
+
+#[tokio::test]
+async fn split_and_reunite() {
+    let (client, server) = tokio::io::duplex(1024);
+    let (mut sink, mut stream) = ClientBuilder::new().take_over(client).split();
+    let mut server = ServerBuilder::new().serve(server);
+
+    let writer = tokio::spawn(async move {
+        sink.send(Message::text("Hello!")).await.unwrap();
+        sink
+    });
+
+    let msg = server.next().await.unwrap().unwrap();
+    assert_eq!(msg.as_text(), Some("Hello!"));
+
+    // The read half answers pings on its own
+    server.send(Message::ping("ping")).await.unwrap();
+    let msg = stream.next().await.unwrap().unwrap();
+    assert!(msg.is_ping());
+    let reader = tokio::spawn(async move {
+        let msg = stream.next().await.unwrap().unwrap();
+        assert_eq!(msg.as_text(), Some("World!"));
+        stream
+    });
+
+    let msg = server.next().await.unwrap().unwrap();
+    assert!(msg.is_pong());
+    assert_eq!(&*msg.into_payload(), b"ping");
+    server.send(Message::text("World!")).await.unwrap();
+
+    let sink = writer.await.unwrap();
+    let stream = reader.await.unwrap();
+    let mut client = sink.reunite(stream).unwrap();
+
+    let (result, ()) = tokio::join!(client.close(), async {
+        assert!(server.next().await.unwrap().unwrap().is_close());
+        assert!(server.next().await.is_none());
+    });
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn reunite_mismatched() {
+    let (a, _) = tokio::io::duplex(1024);
+    let (b, _) = tokio::io::duplex(1024);
+    let (sink, _) = ClientBuilder::new().take_over(a).split();
+    let (_, stream) = ClientBuilder::new().take_over(b).split();
+
+    assert!(sink.reunite(stream).is_err());
+}
@@ -0,0 +1,63 @@
+#![cfg(any(
+    feature = "rustls-native-roots",
+    feature = "rustls-webpki-roots",
+    feature = "rustls-platform-verifier",
+    feature = "rustls-bring-your-own-connector"
+))]
+use std::sync::Arc;
+
+use rustls::{ClientConfig, RootCertStore, crypto::aws_lc_rs};
+use rustls_pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio_websockets::{Connector, Error};
+
+/// Creates a rustls connector that trusts no certificates.
+fn connector() -> Connector {
+    let config = ClientConfig::builder_with_provider(Arc::new(aws_lc_rs::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(RootCertStore::empty())
+        .with_no_client_auth();
+
+    Arc::new(config).into()
+}
+
+#[test]
+fn client_auth_invalid() {
+    let key = || PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(vec![0; 32]));
+
+    assert!(matches!(
+        connector().client_auth(Vec::new(), key()),
+        Err(Error::Rustls(rustls::Error::NoCertificatesPresented))
+    ));
+    assert!(matches!(
+        connector().client_auth(vec![CertificateDer::from(vec![0; 32])], key()),
+        Err(Error::Rustls(_))
+    ));
+}
+
+#[test]
+fn non_rustls_connector_unchanged() {
+    assert!(matches!(
+        Connector::Plain.dangerous_accept_invalid_certs(),
+        Connector::Plain
+    ));
+    assert!(matches!(
+        Connector::Plain.client_auth(Vec::new(), PrivateKeyDer::Pkcs8(Vec::new().into())),
+        Err(Error::NotRustlsConnector)
+    ));
+    assert!(matches!(
+        connector().dangerous_accept_invalid_certs(),
+        Connector::Rustls(_)
+    ));
+}
+
+#[tokio::test]
+async fn plain_stream() {
+    let stream = Connector::Plain
+        .wrap("localhost", tokio::io::empty())
+        .await
+        .unwrap();
+
+    assert!(!stream.is_tls());
+    assert!(stream.tls_info().is_none());
+}
@@ -6,7 +6,7 @@
 
 use bytes::{BufMut, Bytes, BytesMut};
 use futures_util::StreamExt;
-use tokio::io::{AsyncWriteExt, duplex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, duplex};
 use tokio_websockets::{Error, ServerBuilder, proto::ProtocolError};
 
 const MASK: [u8; 4] = [0, 0, 0, 0];
@@ -63,3 +63,24 @@ async fn test_utf8_fail_fast_incomplete_continuation() {
         Some(Err(Error::Protocol(ProtocolError::InvalidUtf8)))
     ));
 }
+
+#[tokio::test]
+async fn test_utf8_close_reason() {
+    let (one, mut two) = duplex(usize::MAX);
+    let mut server = ServerBuilder::new().serve(one);
+
+    // Close code 1000 followed by an invalid UTF-8 reason
+    let frame = encode_frame(8, &[3, 232, 0xFF], 3, true);
+    two.write_all(&frame).await.unwrap();
+
+    assert!(matches!(
+        server.next().await,
+        Some(Err(Error::Protocol(ProtocolError::InvalidUtf8)))
+    ));
+    assert!(server.next().await.is_none());
+
+    // The server fails the connection with 1007 (invalid frame payload data)
+    let mut response = [0; 4];
+    two.read_exact(&mut response).await.unwrap();
+    assert_eq!(response, [0x88, 14, 0x03, 0xEF]);
+}